@@ -0,0 +1,45 @@
+use pentomino_solver::solvers::{
+    DefaultSolver, OptimizedSolver, OptimizedSolverType, SimpleSolver,
+};
+use std::path::Path;
+use std::{env, fs};
+
+const BOARDS: [(usize, usize); 5] = [(3, 20), (4, 15), (5, 12), (6, 10), (8, 8)];
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let mut entries = String::new();
+    if env::var_os("CARGO_FEATURE_EMBED_TABLES").is_some() {
+        for (rows, cols) in BOARDS {
+            for (name, bytes) in [
+                ("simple", SimpleSolver::new(rows, cols).to_bytes()),
+                ("default", DefaultSolver::new(rows, cols).to_bytes()),
+                (
+                    "optimized-small",
+                    OptimizedSolver::new(rows, cols, OptimizedSolverType::SmallTable).to_bytes(),
+                ),
+                (
+                    "optimized-large",
+                    OptimizedSolver::new(rows, cols, OptimizedSolverType::LargeTable).to_bytes(),
+                ),
+                (
+                    "optimized-most-constrained",
+                    OptimizedSolver::new(rows, cols, OptimizedSolverType::MostConstrained)
+                        .to_bytes(),
+                ),
+            ] {
+                let file = format!("{name}-{rows}x{cols}.bin");
+                fs::write(Path::new(&out_dir).join(&file), bytes).unwrap();
+                entries += &format!(
+                    "    ((\"{name}\", {rows}, {cols}), include_bytes!(concat!(env!(\"OUT_DIR\"), \"/{file}\"))),\n"
+                );
+            }
+        }
+    }
+    fs::write(
+        Path::new(&out_dir).join("tables.rs"),
+        format!("const EMBEDDED_TABLES: &[EmbeddedTable] = &[\n{entries}];\n"),
+    )
+    .unwrap();
+}
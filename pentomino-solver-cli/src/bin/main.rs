@@ -1,12 +1,20 @@
 use clap::{CommandFactory, Parser, ValueEnum};
 use colored::*;
-use pentomino_solver::solvers::OptimizedSolverType;
-use pentomino_solver::solvers::{DefaultSolver, OptimizedSolver, SimpleSolver};
+use pentomino_solver::board;
+#[cfg(feature = "sat")]
+use pentomino_solver::solvers::SatSolver;
+use pentomino_solver::solvers::{DefaultSolver, LocalSearchSolver, OptimizedSolver, SimpleSolver};
+use pentomino_solver::solvers::{OptimizedSolverType, ScanOrder};
 use pentomino_solver::Piece;
 use pentomino_solver::Solver as PentominoSolver;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use supports_color::Stream;
 
+/// Table blob keyed by `(solver, rows, cols)`.
+type EmbeddedTable = ((&'static str, usize, usize), &'static [u8]);
+
+include!(concat!(env!("OUT_DIR"), "/tables.rs"));
+
 /// Pentomino solver CLI application
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -26,6 +34,9 @@ struct Args {
     /// Solver type
     #[arg(short, long, value_enum, default_value_t = Solver::Default)]
     solver: Solver,
+    /// Cell scan order (optimized solvers only)
+    #[arg(short, long, value_enum)]
+    order: Option<Order>,
 }
 
 #[derive(Clone, Copy, Debug, ValueEnum)]
@@ -43,23 +54,87 @@ enum Solver {
     Default,
     OptimizedSmall,
     OptimizedLarge,
+    OptimizedMostConstrained,
+    Local,
+    #[cfg(feature = "sat")]
+    Sat,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Order {
+    RowMajor,
+    ColumnMajor,
+    Boustrophedon,
+    Spiral,
+    Auto,
+}
+
+impl Board {
+    fn board(&self) -> board::Board {
+        match self {
+            Board::Rect3x20 => board::Board::new(3, 20, &[]),
+            Board::Rect4x15 => board::Board::new(4, 15, &[]),
+            Board::Rect5x12 => board::Board::new(5, 12, &[]),
+            Board::Rect6x10 => board::Board::new(6, 10, &[]),
+            Board::Rect8x8_2x2 => board::Board::new(8, 8, &[(3, 3), (4, 3), (3, 4), (4, 4)]),
+        }
+        .unwrap()
+    }
 }
 
 impl Solver {
-    fn create_solver(&self, rows: usize, cols: usize) -> Box<dyn PentominoSolver> {
+    fn is_optimized(&self) -> bool {
+        matches!(
+            self,
+            Solver::OptimizedSmall | Solver::OptimizedLarge | Solver::OptimizedMostConstrained
+        )
+    }
+    fn create_solver(
+        &self,
+        rows: usize,
+        cols: usize,
+        initial: u64,
+        order: Option<Order>,
+    ) -> Box<dyn PentominoSolver> {
+        let name = self.to_possible_value().unwrap().get_name().to_string();
+        if order.is_none() {
+            if let Some((_, bytes)) = EMBEDDED_TABLES
+                .iter()
+                .find(|(key, _)| *key == (name.as_str(), rows, cols))
+            {
+                return match self {
+                    Solver::Simple => Box::new(SimpleSolver::from_bytes(bytes).unwrap()),
+                    Solver::Default => Box::new(DefaultSolver::from_bytes(bytes).unwrap()),
+                    _ => Box::new(OptimizedSolver::from_bytes(bytes).unwrap()),
+                };
+            }
+        }
+        let optimized = |solver_type| {
+            let order = match order {
+                None => return OptimizedSolver::new(rows, cols, solver_type),
+                Some(Order::Auto) => {
+                    return OptimizedSolver::with_auto_scan_order(rows, cols, solver_type, initial)
+                }
+                Some(Order::RowMajor) => ScanOrder::RowMajor,
+                Some(Order::ColumnMajor) => ScanOrder::ColumnMajor,
+                Some(Order::Boustrophedon) => ScanOrder::Boustrophedon,
+                Some(Order::Spiral) => ScanOrder::Spiral,
+            };
+            OptimizedSolver::with_scan_order(rows, cols, solver_type, order).unwrap()
+        };
         match self {
             Solver::Simple => Box::new(SimpleSolver::new(rows, cols)),
             Solver::Default => Box::new(DefaultSolver::new(rows, cols)),
-            Solver::OptimizedSmall => Box::new(OptimizedSolver::new(
-                rows,
-                cols,
-                OptimizedSolverType::SmallTable,
-            )),
-            Solver::OptimizedLarge => Box::new(OptimizedSolver::new(
-                rows,
-                cols,
-                OptimizedSolverType::LargeTable,
-            )),
+            Solver::OptimizedSmall => Box::new(optimized(OptimizedSolverType::SmallTable)),
+            Solver::OptimizedLarge => Box::new(optimized(OptimizedSolverType::LargeTable)),
+            Solver::OptimizedMostConstrained => {
+                Box::new(optimized(OptimizedSolverType::MostConstrained))
+            }
+            Solver::Local => {
+                Box::new(LocalSearchSolver::new(rows, cols, Duration::from_secs(10), 0).unwrap())
+            }
+            #[cfg(feature = "sat")]
+            Solver::Sat => Box::new(SatSolver::new(rows, cols)),
         }
     }
 }
@@ -105,18 +180,18 @@ fn main() {
             )
             .exit();
     }
+    if args.order.is_some() && !args.solver.is_optimized() {
+        Args::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "--order is only supported by the optimized solvers",
+            )
+            .exit();
+    }
 
-    let ((rows, cols), initial) = match args.board {
-        Board::Rect3x20 => ((3, 20), 0),
-        Board::Rect4x15 => ((4, 15), 0),
-        Board::Rect5x12 => ((5, 12), 0),
-        Board::Rect6x10 => ((6, 10), 0),
-        Board::Rect8x8_2x2 => (
-            (8, 8),
-            [27, 28, 35, 36].iter().map(|&p| 1 << p).sum::<u64>(),
-        ),
-    };
-    let solver = args.solver.create_solver(rows, cols);
+    let board = args.board.board();
+    let (rows, cols, initial) = (board.rows(), board.cols(), board.initial());
+    let solver = args.solver.create_solver(rows, cols, initial, args.order);
     let (solutions, elapsed) = {
         let now = Instant::now();
         let solutions = solver.solve(initial, args.unique);
@@ -137,3 +212,28 @@ fn main() {
     }
     println!("Found {} solutions in {elapsed:?}", solutions.len());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pentomino_solver::solvers::SatSolver;
+
+    #[test]
+    fn presets_round_trip() {
+        for preset in Board::value_variants() {
+            let board = preset.board();
+            let json = serde_json::to_string(&board).unwrap();
+            assert_eq!(serde_json::from_str::<board::Board>(&json).unwrap(), board);
+
+            let solver = SatSolver::new(board.rows(), board.cols());
+            let model = solver.encode(board.initial(), false).solve().unwrap();
+            let solution = solver.decode(board.initial(), &model).unwrap();
+            let grid = serde_json::to_string(&board.grid(&solution)).unwrap();
+            let grid = serde_json::from_str::<board::Grid>(&grid).unwrap();
+            assert_eq!(grid.solution(), Ok((board, solution)));
+            let placements = serde_json::to_string(&board.placements(&solution)).unwrap();
+            let placements = serde_json::from_str::<Vec<board::Placement>>(&placements).unwrap();
+            assert_eq!(board.solution(&placements), Ok(solution));
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use pentomino_solver::solvers::{OptimizedSolver, OptimizedSolverType};
+use std::path::Path;
+use std::{env, fs};
+
+const BOARDS: [(usize, usize); 5] = [(3, 20), (4, 15), (5, 12), (6, 10), (8, 8)];
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let mut entries = String::new();
+    if env::var_os("CARGO_FEATURE_EMBED_TABLES").is_some() {
+        for (rows, cols) in BOARDS {
+            for large_table in [false, true] {
+                let solver_type = if large_table {
+                    OptimizedSolverType::LargeTable
+                } else {
+                    OptimizedSolverType::SmallTable
+                };
+                let file = format!("{rows}x{cols}-{large_table}.bin");
+                let bytes = OptimizedSolver::new(rows, cols, solver_type).to_bytes();
+                fs::write(Path::new(&out_dir).join(&file), bytes).unwrap();
+                entries += &format!(
+                    "    (({rows}, {cols}, {large_table}), include_bytes!(concat!(env!(\"OUT_DIR\"), \"/{file}\"))),\n"
+                );
+            }
+        }
+    }
+    fs::write(
+        Path::new(&out_dir).join("tables.rs"),
+        format!("const EMBEDDED_TABLES: &[EmbeddedTable] = &[\n{entries}];\n"),
+    )
+    .unwrap();
+}
@@ -3,6 +3,11 @@ use pentomino_solver::solvers::{OptimizedSolver, OptimizedSolverType};
 use pentomino_solver::{Bitboard, Solver, NUM_PIECES};
 use wasm_bindgen::prelude::*;
 
+/// Table blob keyed by `(rows, cols, large_table)`.
+type EmbeddedTable = ((usize, usize, bool), &'static [u8]);
+
+include!(concat!(env!("OUT_DIR"), "/tables.rs"));
+
 #[wasm_bindgen]
 pub struct Solutions(Vec<[Bitboard; NUM_PIECES]>);
 
@@ -19,8 +24,14 @@ impl WasmSolver {
         } else {
             OptimizedSolverType::SmallTable
         };
+        let embedded = EMBEDDED_TABLES
+            .iter()
+            .find(|(key, _)| *key == (rows, cols, large_table));
         Self {
-            inner: OptimizedSolver::new(rows, cols, solver_type),
+            inner: match embedded {
+                Some((_, bytes)) => OptimizedSolver::from_bytes(bytes).unwrap(),
+                None => OptimizedSolver::new(rows, cols, solver_type),
+            },
         }
     }
     pub fn solve(&self, initial: Bitboard, unique: bool) -> Solutions {
@@ -7,7 +7,7 @@ use pentomino_solver::Solver;
 #[bench]
 fn bench_8x8_2x2_simple(b: &mut test::Bencher) {
     let solver = SimpleSolver::new(8, 8);
-    let initial = [27, 28, 35, 36].iter().map(|&p| 1 << p).sum::<u64>().into();
+    let initial = [27, 28, 35, 36].iter().map(|&p| 1 << p).sum::<u64>();
     b.iter(|| {
         let solutions = solver.solve(initial, false);
         assert_eq!(solutions.len(), 520);
@@ -17,7 +17,7 @@ fn bench_8x8_2x2_simple(b: &mut test::Bencher) {
 #[bench]
 fn bench_8x8_2x2_default(b: &mut test::Bencher) {
     let solver = DefaultSolver::new(8, 8);
-    let initial = [27, 28, 35, 36].iter().map(|&p| 1 << p).sum::<u64>().into();
+    let initial = [27, 28, 35, 36].iter().map(|&p| 1 << p).sum::<u64>();
     b.iter(|| {
         let solutions = solver.solve(initial, false);
         assert_eq!(solutions.len(), 520);
@@ -27,7 +27,7 @@ fn bench_8x8_2x2_default(b: &mut test::Bencher) {
 #[bench]
 fn bench_8x8_2x2_optimized_small(b: &mut test::Bencher) {
     let solver = OptimizedSolver::new(8, 8, OptimizedSolverType::SmallTable);
-    let initial = [27, 28, 35, 36].iter().map(|&p| 1 << p).sum::<u64>().into();
+    let initial = [27, 28, 35, 36].iter().map(|&p| 1 << p).sum::<u64>();
     b.iter(|| {
         let solutions = solver.solve(initial, false);
         assert_eq!(solutions.len(), 520);
@@ -37,7 +37,7 @@ fn bench_8x8_2x2_optimized_small(b: &mut test::Bencher) {
 #[bench]
 fn bench_8x8_2x2_optimized_large(b: &mut test::Bencher) {
     let solver = OptimizedSolver::new(8, 8, OptimizedSolverType::LargeTable);
-    let initial = [27, 28, 35, 36].iter().map(|&p| 1 << p).sum::<u64>().into();
+    let initial = [27, 28, 35, 36].iter().map(|&p| 1 << p).sum::<u64>();
     b.iter(|| {
         let solutions = solver.solve(initial, false);
         assert_eq!(solutions.len(), 520);
@@ -47,7 +47,7 @@ fn bench_8x8_2x2_optimized_large(b: &mut test::Bencher) {
 #[bench]
 fn bench_8x8_2x2_default_unique(b: &mut test::Bencher) {
     let solver = DefaultSolver::new(8, 8);
-    let initial = [27, 28, 35, 36].iter().map(|&p| 1 << p).sum::<u64>().into();
+    let initial = [27, 28, 35, 36].iter().map(|&p| 1 << p).sum::<u64>();
     b.iter(|| {
         let solutions = solver.solve(initial, true);
         assert_eq!(solutions.len(), 65);
@@ -57,7 +57,7 @@ fn bench_8x8_2x2_default_unique(b: &mut test::Bencher) {
 #[bench]
 fn bench_8x8_2x2_optimized_small_unique(b: &mut test::Bencher) {
     let solver = OptimizedSolver::new(8, 8, OptimizedSolverType::SmallTable);
-    let initial = [27, 28, 35, 36].iter().map(|&p| 1 << p).sum::<u64>().into();
+    let initial = [27, 28, 35, 36].iter().map(|&p| 1 << p).sum::<u64>();
     b.iter(|| {
         let solutions = solver.solve(initial, true);
         assert_eq!(solutions.len(), 65);
@@ -67,7 +67,7 @@ fn bench_8x8_2x2_optimized_small_unique(b: &mut test::Bencher) {
 #[bench]
 fn bench_8x8_2x2_optimized_large_unique(b: &mut test::Bencher) {
     let solver = OptimizedSolver::new(8, 8, OptimizedSolverType::LargeTable);
-    let initial = [27, 28, 35, 36].iter().map(|&p| 1 << p).sum::<u64>().into();
+    let initial = [27, 28, 35, 36].iter().map(|&p| 1 << p).sum::<u64>();
     b.iter(|| {
         let solutions = solver.solve(initial, true);
         assert_eq!(solutions.len(), 65);
@@ -233,3 +233,25 @@ fn bench_3x20_optimized_large_unique(b: &mut test::Bencher) {
         assert_eq!(solutions.len(), 2);
     });
 }
+
+#[bench]
+fn bench_6x10_optimized_large_new(b: &mut test::Bencher) {
+    b.iter(|| OptimizedSolver::new(6, 10, OptimizedSolverType::LargeTable));
+}
+
+#[bench]
+fn bench_6x10_optimized_large_from_bytes(b: &mut test::Bencher) {
+    let bytes = OptimizedSolver::new(6, 10, OptimizedSolverType::LargeTable).to_bytes();
+    b.iter(|| OptimizedSolver::from_bytes(&bytes).unwrap());
+}
+
+#[bench]
+fn bench_6x10_optimized_small_new(b: &mut test::Bencher) {
+    b.iter(|| OptimizedSolver::new(6, 10, OptimizedSolverType::SmallTable));
+}
+
+#[bench]
+fn bench_6x10_optimized_small_from_bytes(b: &mut test::Bencher) {
+    let bytes = OptimizedSolver::new(6, 10, OptimizedSolverType::SmallTable).to_bytes();
+    b.iter(|| OptimizedSolver::from_bytes(&bytes).unwrap());
+}
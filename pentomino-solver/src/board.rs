@@ -0,0 +1,385 @@
+//! Boards and solutions in coordinates and pieces.
+//!
+//! The solvers take a board as its size and a bitboard of filled cells, with
+//! the cell `(x, y)` at bit `x + y * cols`, and return solutions as a bitboard
+//! per piece. `Board` names a board by its size and holes, and a solution can
+//! be written as a `Grid` of letters or as a list of `Placement`s.
+//!
+//! With the `serde` feature these types serialize to the following JSON,
+//! which is kept stable:
+//!
+//! - `Piece`: its letter, such as `"X"`.
+//! - `Board`: `{"rows":8,"cols":8,"holes":[[3,3],[4,3],[3,4],[4,4]]}`, with
+//!   every hole as `[x, y]` and in cell order. `holes` may be left out of a
+//!   board without any.
+//! - `Grid`: one string per row, with the letter of the piece on every cell
+//!   and `.` on holes, such as `["UUXPPPPP..", ...]`.
+//! - `Placement`: `{"piece":"X","cells":[[1,0],[0,1],[1,1],[2,1],[1,2]]}`,
+//!   and a solution as the list of its placements in piece order.
+//! - The solver options `OptimizedSolverType`, `ScanOrder` and
+//!   `milp::Objective`: the variant name in snake case, such as
+//!   `"most_constrained"`, or `{"custom":[...]}` for a custom scan order.
+
+use crate::shapes::{calculate_shapes, orientation};
+use crate::solvers::represent;
+use crate::{Bitboard, Piece, NUM_PIECES};
+use derive_more::Display;
+use num_traits::FromPrimitive;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const LETTERS: &str = "OPQRSTUVWXYZ";
+
+#[derive(Debug, Display, Clone, PartialEq, Eq)]
+pub enum BoardError {
+    #[display(fmt = "a {}x{} board must have between 1 and 64 cells", _0, _1)]
+    InvalidSize(usize, usize),
+    #[display(fmt = "cell ({}, {}) is off the board", _0, _1)]
+    OffBoard(usize, usize),
+    #[display(fmt = "invalid cell {:?}", _0)]
+    InvalidCell(char),
+    #[display(fmt = "rows of the grid differ in length")]
+    RaggedGrid,
+    #[display(fmt = "cells of {} are not a placement", _0)]
+    NotAPlacement(Piece),
+    #[display(fmt = "{} is placed more than once", _0)]
+    DuplicatePiece(Piece),
+    #[display(fmt = "{} overlaps a filled cell or another piece", _0)]
+    Overlap(Piece),
+    #[display(fmt = "placements do not tile the board")]
+    NotATiling,
+}
+
+impl std::error::Error for BoardError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "BoardRepr", into = "BoardRepr")
+)]
+pub struct Board {
+    rows: usize,
+    cols: usize,
+    initial: Bitboard,
+}
+
+impl Board {
+    /// The board of the given size with holes at the cells `(x, y)`.
+    pub fn new(rows: usize, cols: usize, holes: &[(usize, usize)]) -> Result<Self, BoardError> {
+        if rows == 0 || cols == 0 || rows * cols > 64 {
+            return Err(BoardError::InvalidSize(rows, cols));
+        }
+        let mut initial = 0;
+        for &(x, y) in holes {
+            if x >= cols || y >= rows {
+                return Err(BoardError::OffBoard(x, y));
+            }
+            initial |= 1 << (x + y * cols);
+        }
+        Ok(Self {
+            rows,
+            cols,
+            initial,
+        })
+    }
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+    /// The holes as the bitboard that the solvers take as `initial`.
+    pub fn initial(&self) -> Bitboard {
+        self.initial
+    }
+    /// The holes as `(x, y)`, in cell order.
+    pub fn holes(&self) -> Vec<(usize, usize)> {
+        (0..self.rows * self.cols)
+            .filter(|&z| self.initial & (1 << z) != 0)
+            .map(|z| (z % self.cols, z / self.cols))
+            .collect()
+    }
+    /// The solution as a grid, as `Solver::represent_solution` shows it.
+    pub fn grid(&self, pieces: &[Bitboard; NUM_PIECES]) -> Grid {
+        Grid(represent(self.rows, self.cols, pieces))
+    }
+    /// The placements of the pieces of the solution, in piece order.
+    pub fn placements(&self, pieces: &[Bitboard; NUM_PIECES]) -> Vec<Placement> {
+        pieces
+            .iter()
+            .enumerate()
+            .map(|(i, &b)| Placement {
+                piece: Piece::from_usize(i).unwrap(),
+                cells: (0..self.rows * self.cols)
+                    .filter(|&z| b & (1 << z) != 0)
+                    .map(|z| (z % self.cols, z / self.cols))
+                    .collect(),
+            })
+            .collect()
+    }
+    /// The solution made of the placements, which must place every piece
+    /// once and tile the board.
+    pub fn solution(&self, placements: &[Placement]) -> Result<[Bitboard; NUM_PIECES], BoardError> {
+        let shapes = calculate_shapes();
+        let mut pieces = [0; NUM_PIECES];
+        let mut covered = self.initial;
+        for placement in placements {
+            let i = placement.piece as usize;
+            if pieces[i] != 0 {
+                return Err(BoardError::DuplicatePiece(placement.piece));
+            }
+            let mut b: Bitboard = 0;
+            for &(x, y) in &placement.cells {
+                if x >= self.cols || y >= self.rows {
+                    return Err(BoardError::OffBoard(x, y));
+                }
+                b |= 1 << (x + y * self.cols);
+            }
+            if orientation(&shapes, i, &placement.cells).is_none() {
+                return Err(BoardError::NotAPlacement(placement.piece));
+            }
+            if covered & b != 0 {
+                return Err(BoardError::NotATiling);
+            }
+            pieces[i] = b;
+            covered |= b;
+        }
+        if covered.count_ones() as usize != self.rows * self.cols {
+            return Err(BoardError::NotATiling);
+        }
+        Ok(pieces)
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BoardRepr {
+    rows: usize,
+    cols: usize,
+    #[serde(default)]
+    holes: Vec<(usize, usize)>,
+}
+
+#[cfg(feature = "serde")]
+impl From<Board> for BoardRepr {
+    fn from(board: Board) -> Self {
+        Self {
+            rows: board.rows,
+            cols: board.cols,
+            holes: board.holes(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<BoardRepr> for Board {
+    type Error = BoardError;
+
+    fn try_from(repr: BoardRepr) -> Result<Self, Self::Error> {
+        Self::new(repr.rows, repr.cols, &repr.holes)
+    }
+}
+
+/// A solution as the piece on every cell by row, with `None` on holes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "Vec<String>", into = "Vec<String>")
+)]
+pub struct Grid(pub Vec<Vec<Option<Piece>>>);
+
+impl Grid {
+    /// The board of the grid and the solution on it.
+    pub fn solution(&self) -> Result<(Board, [Bitboard; NUM_PIECES]), BoardError> {
+        let (rows, cols) = (self.0.len(), self.0.first().map_or(0, Vec::len));
+        let mut holes = Vec::new();
+        let mut placements = Vec::<Placement>::new();
+        for (y, row) in self.0.iter().enumerate() {
+            if row.len() != cols {
+                return Err(BoardError::RaggedGrid);
+            }
+            for (x, &p) in row.iter().enumerate() {
+                match p {
+                    None => holes.push((x, y)),
+                    Some(piece) => match placements.iter_mut().find(|q| q.piece == piece) {
+                        Some(q) => q.cells.push((x, y)),
+                        None => placements.push(Placement {
+                            piece,
+                            cells: vec![(x, y)],
+                        }),
+                    },
+                }
+            }
+        }
+        let board = Board::new(rows, cols, &holes)?;
+        Ok((board, board.solution(&placements)?))
+    }
+}
+
+impl TryFrom<Vec<String>> for Grid {
+    type Error = BoardError;
+
+    fn try_from(lines: Vec<String>) -> Result<Self, Self::Error> {
+        let rows = lines
+            .iter()
+            .map(|line| {
+                line.chars()
+                    .map(|c| match (c, LETTERS.find(c)) {
+                        ('.', _) => Ok(None),
+                        (_, Some(i)) => Ok(Piece::from_usize(i)),
+                        _ => Err(BoardError::InvalidCell(c)),
+                    })
+                    .collect()
+            })
+            .collect::<Result<Vec<Vec<_>>, _>>()?;
+        if rows.iter().any(|row| row.len() != rows[0].len()) {
+            return Err(BoardError::RaggedGrid);
+        }
+        Ok(Self(rows))
+    }
+}
+
+impl From<Grid> for Vec<String> {
+    fn from(grid: Grid) -> Self {
+        grid.0
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|p| p.map_or('.', |p| LETTERS.as_bytes()[p as usize] as char))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// The cells `(x, y)` covered by a piece.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Placement {
+    pub piece: Piece,
+    pub cells: Vec<(usize, usize)>,
+}
+
+/// A solution of the 6x10 board shared by the tests, the one numbered 1234
+/// by `codec::Ranking`.
+#[cfg(test)]
+pub(crate) const GRID_6X10: [&str; 6] = [
+    "OOOOOPPVVV",
+    "UUUZZPPWWV",
+    "UXUTZPWWRV",
+    "XXXTZZWRRR",
+    "QXTTTYSSSR",
+    "QQQQYYYYSS",
+];
+
+/// `GRID_6X10` as bitboards.
+#[cfg(test)]
+pub(crate) fn solution_6x10() -> [Bitboard; NUM_PIECES] {
+    let grid = Grid::try_from(GRID_6X10.map(String::from).to_vec()).unwrap();
+    grid.solution().unwrap().1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solvers::DefaultSolver;
+    use crate::Solver;
+
+    #[test]
+    fn conversions() {
+        let board = Board::new(8, 8, &[(3, 3), (4, 3), (3, 4), (4, 4)]).unwrap();
+        assert_eq!(
+            board.initial(),
+            (1 << 27) | (1 << 28) | (1 << 35) | (1 << 36)
+        );
+        assert_eq!(board.holes(), [(3, 3), (4, 3), (3, 4), (4, 4)]);
+        let solver = DefaultSolver::new(8, 8);
+        let solution = solver.solve(board.initial(), true)[0];
+
+        let grid = board.grid(&solution);
+        assert_eq!(grid.0, solver.represent_solution(&solution));
+        assert_eq!(grid.solution(), Ok((board, solution)));
+        let lines = Vec::<String>::from(grid.clone());
+        assert_eq!(lines[3].chars().filter(|&c| c == '.').count(), 2);
+        assert_eq!(Grid::try_from(lines), Ok(grid));
+
+        let mut placements = board.placements(&solution);
+        assert_eq!(placements[Piece::X as usize].piece, Piece::X);
+        assert_eq!(board.solution(&placements), Ok(solution));
+        placements.swap(0, 11);
+        assert_eq!(board.solution(&placements), Ok(solution));
+
+        let mut p = placements.clone();
+        p[0].cells[0] = p[1].cells[0];
+        assert_eq!(
+            board.solution(&p),
+            Err(BoardError::NotAPlacement(p[0].piece))
+        );
+        assert_eq!(
+            board.solution(&placements[1..]),
+            Err(BoardError::NotATiling)
+        );
+        let mut p = placements.clone();
+        p[1] = p[0].clone();
+        assert_eq!(
+            board.solution(&p),
+            Err(BoardError::DuplicatePiece(p[0].piece))
+        );
+        assert_eq!(Board::new(9, 8, &[]), Err(BoardError::InvalidSize(9, 8)));
+        assert_eq!(Board::new(8, 8, &[(8, 0)]), Err(BoardError::OffBoard(8, 0)));
+        assert_eq!(
+            Grid::try_from(vec!["OO".to_string(), "O".to_string()]),
+            Err(BoardError::RaggedGrid)
+        );
+        assert_eq!(
+            Grid::try_from(vec!["F".to_string()]),
+            Err(BoardError::InvalidCell('F'))
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json() {
+        let board = Board::new(8, 8, &[(3, 3), (4, 3), (3, 4), (4, 4)]).unwrap();
+        let json = r#"{"rows":8,"cols":8,"holes":[[3,3],[4,3],[3,4],[4,4]]}"#;
+        assert_eq!(serde_json::to_string(&board).unwrap(), json);
+        assert_eq!(serde_json::from_str::<Board>(json).unwrap(), board);
+        assert_eq!(
+            serde_json::from_str::<Board>(r#"{"rows":6,"cols":10}"#).unwrap(),
+            Board::new(6, 10, &[]).unwrap()
+        );
+        assert!(serde_json::from_str::<Board>(r#"{"rows":6,"cols":10,"holes":[[10,0]]}"#).is_err());
+
+        assert_eq!(serde_json::to_string(&Piece::X).unwrap(), r#""X""#);
+        let placement = Placement {
+            piece: Piece::X,
+            cells: vec![(1, 0), (0, 1), (1, 1), (2, 1), (1, 2)],
+        };
+        let json = r#"{"piece":"X","cells":[[1,0],[0,1],[1,1],[2,1],[1,2]]}"#;
+        assert_eq!(serde_json::to_string(&placement).unwrap(), json);
+        assert_eq!(serde_json::from_str::<Placement>(json).unwrap(), placement);
+
+        let grid = Grid(vec![vec![Some(Piece::O), None]]);
+        assert_eq!(serde_json::to_string(&grid).unwrap(), r#"["O."]"#);
+        assert_eq!(serde_json::from_str::<Grid>(r#"["O."]"#).unwrap(), grid);
+        assert!(serde_json::from_str::<Grid>(r#"["O", "OO"]"#).is_err());
+
+        use crate::solvers::{OptimizedSolverType, ScanOrder};
+        assert_eq!(
+            serde_json::to_string(&OptimizedSolverType::MostConstrained).unwrap(),
+            r#""most_constrained""#
+        );
+        assert_eq!(
+            serde_json::to_string(&ScanOrder::custom(vec![1, 0]).unwrap()).unwrap(),
+            r#"{"custom":[1,0]}"#
+        );
+        assert!(serde_json::from_str::<ScanOrder>(r#"{"custom":[1,1]}"#).is_err());
+        assert_eq!(
+            serde_json::from_str::<crate::milp::Objective>(r#""max_packing""#).unwrap(),
+            crate::milp::Objective::MaxPacking
+        );
+    }
+}
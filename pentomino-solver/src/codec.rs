@@ -0,0 +1,258 @@
+//! Compact encodings of solutions.
+//!
+//! `SolutionCodec` stores a solution of a board as the index of every piece's
+//! placement among all placements of that piece on the board, packed into as
+//! few bits as the board needs: 11 bytes on a 6x10 board instead of the 96 of
+//! twelve bitboards.
+//!
+//! `Ranking` numbers the solutions of a board from zero, in the order of a
+//! search that fills the first empty cell, scanning row by row (or column by
+//! column on a board wider than tall), and tries the placements covering it
+//! by piece and then by their cells in scan order. The order only depends on
+//! the board, so a solution's number stays the same across versions.
+
+use crate::sampling::transpose;
+use crate::solvers::DefaultSolver;
+use crate::zdd::Zdd;
+use crate::{Bitboard, Piece, NUM_PIECES};
+use derive_more::Display;
+use num_traits::FromPrimitive;
+
+#[derive(Debug, Display, Clone, PartialEq, Eq)]
+pub enum CodecError {
+    #[display(fmt = "{} is not placed on the board", _0)]
+    NotAPlacement(Piece),
+    #[display(fmt = "expected {} bytes, got {}", _0, _1)]
+    WrongLength(usize, usize),
+    #[display(fmt = "placement {} of {} out of range", _1, _0)]
+    IndexOutOfRange(Piece, usize),
+    #[display(fmt = "pieces overlap")]
+    Overlap,
+}
+
+impl std::error::Error for CodecError {}
+
+pub struct SolutionCodec {
+    /// Placements of every piece, sorted.
+    placements: [Vec<Bitboard>; NUM_PIECES],
+    /// Bits of every piece's index.
+    widths: [u32; NUM_PIECES],
+}
+
+impl SolutionCodec {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        let mut placements: [Vec<Bitboard>; NUM_PIECES] = Default::default();
+        for (i, b) in DefaultSolver::new(rows, cols).placements() {
+            placements[i].push(b);
+        }
+        placements.iter_mut().for_each(|v| v.sort_unstable());
+        // no bits for a piece with one placement, or none on a narrow board
+        let widths = std::array::from_fn(|i| {
+            usize::BITS - placements[i].len().saturating_sub(1).leading_zeros()
+        });
+        Self { placements, widths }
+    }
+    /// Placements of piece `i`, in index order.
+    pub(crate) fn placements(&self, i: usize) -> &[Bitboard] {
+        &self.placements[i]
+    }
+    /// Index of the placement of piece `i` in an encoded solution.
+    pub(crate) fn index(&self, bytes: &[u8], i: usize) -> usize {
+        let pos = self.widths[..i].iter().sum::<u32>() as usize;
+        (0..self.widths[i] as usize)
+            .filter(|bit| bytes[(pos + bit) / 8] & (1 << ((pos + bit) % 8)) != 0)
+            .map(|bit| 1 << bit)
+            .sum()
+    }
+    /// Length of every encoded solution.
+    pub fn encoded_len(&self) -> usize {
+        (self.widths.iter().sum::<u32>() as usize).div_ceil(8)
+    }
+    pub fn encode(&self, pieces: &[Bitboard; NUM_PIECES]) -> Result<Vec<u8>, CodecError> {
+        let mut ret = vec![0; self.encoded_len()];
+        let mut pos = 0;
+        for (i, b) in pieces.iter().enumerate() {
+            let k = self.placements[i]
+                .binary_search(b)
+                .map_err(|_| CodecError::NotAPlacement(Piece::from_usize(i).unwrap()))?;
+            // little-endian bits, from the lowest bit of the first byte
+            for bit in 0..self.widths[i] as usize {
+                if k & (1 << bit) != 0 {
+                    ret[(pos + bit) / 8] |= 1 << ((pos + bit) % 8);
+                }
+            }
+            pos += self.widths[i] as usize;
+        }
+        Ok(ret)
+    }
+    pub fn decode(&self, bytes: &[u8]) -> Result<[Bitboard; NUM_PIECES], CodecError> {
+        if bytes.len() != self.encoded_len() {
+            return Err(CodecError::WrongLength(self.encoded_len(), bytes.len()));
+        }
+        let mut pieces = [0; NUM_PIECES];
+        let mut covered = 0;
+        for (i, p) in pieces.iter_mut().enumerate() {
+            let k = self.index(bytes, i);
+            let &b = self.placements[i]
+                .get(k)
+                .ok_or(CodecError::IndexOutOfRange(
+                    Piece::from_usize(i).unwrap(),
+                    k,
+                ))?;
+            if covered & b != 0 {
+                return Err(CodecError::Overlap);
+            }
+            *p = b;
+            covered |= b;
+        }
+        Ok(pieces)
+    }
+}
+
+pub struct Ranking {
+    rows: usize,
+    cols: usize,
+    /// Whether the diagram is built on the transposed board, so that its
+    /// rows are the short side.
+    transposed: bool,
+    zdd: Zdd,
+    /// Number of solutions below every node of `zdd`.
+    counts: Vec<u64>,
+}
+
+impl Ranking {
+    /// Numbers the solutions with the cells of `initial` filled.
+    pub fn new(rows: usize, cols: usize, initial: Bitboard) -> Self {
+        let transposed = cols > rows;
+        let zdd = if transposed {
+            DefaultSolver::new(cols, rows).zdd(transpose(rows, cols, initial))
+        } else {
+            DefaultSolver::new(rows, cols).zdd(initial)
+        };
+        Self {
+            rows,
+            cols,
+            transposed,
+            counts: zdd.counts(),
+            zdd,
+        }
+    }
+    pub fn count(&self) -> u64 {
+        self.zdd.count()
+    }
+    /// Number of the solution, or `None` if it isn't a solution of the board.
+    pub fn rank(&self, pieces: &[Bitboard; NUM_PIECES]) -> Option<u64> {
+        if self.transposed {
+            let pieces = pieces.map(|b| transpose(self.rows, self.cols, b));
+            self.zdd.rank(&self.counts, &pieces)
+        } else {
+            self.zdd.rank(&self.counts, pieces)
+        }
+    }
+    /// The solution numbered `r`, or `None` if `r` is not below `count`.
+    pub fn unrank(&self, r: u64) -> Option<[Bitboard; NUM_PIECES]> {
+        let pieces = self.zdd.unrank(&self.counts, r)?;
+        if self.transposed {
+            Some(pieces.map(|b| transpose(self.cols, self.rows, b)))
+        } else {
+            Some(pieces)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Board, GRID_6X10};
+    use crate::Solver;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn encode_decode() {
+        let codec = SolutionCodec::new(6, 10);
+        assert_eq!(codec.encoded_len(), 11);
+        let ranking = Ranking::new(6, 10, 0);
+        let solutions = (0..ranking.count())
+            .map(|r| ranking.unrank(r).unwrap())
+            .collect::<Vec<_>>();
+        let mut encoded = BTreeSet::new();
+        for s in &solutions {
+            let bytes = codec.encode(s).unwrap();
+            assert_eq!(codec.decode(&bytes), Ok(*s));
+            encoded.insert(bytes);
+        }
+        assert_eq!(encoded.len(), solutions.len());
+
+        let mut pieces = solutions[0];
+        pieces[Piece::X as usize] = 0;
+        assert_eq!(
+            codec.encode(&pieces),
+            Err(CodecError::NotAPlacement(Piece::X))
+        );
+        assert_eq!(codec.decode(&[0; 10]), Err(CodecError::WrongLength(11, 10)));
+        assert_eq!(codec.decode(&[0; 11]), Err(CodecError::Overlap));
+        assert!(matches!(
+            codec.decode(&[0xff; 11]),
+            Err(CodecError::IndexOutOfRange(Piece::O, _))
+        ));
+    }
+
+    #[test]
+    fn narrow_boards() {
+        // X doesn't fit in two rows, and O only fits one way in one
+        let codec = SolutionCodec::new(2, 30);
+        assert!(codec.placements(Piece::X as usize).is_empty());
+        let solution = [0; NUM_PIECES];
+        assert_eq!(
+            codec.encode(&solution),
+            Err(CodecError::NotAPlacement(Piece::O))
+        );
+        let codec = SolutionCodec::new(1, 5);
+        assert_eq!(codec.placements(Piece::O as usize), [0b11111]);
+        assert_eq!(codec.encoded_len(), 0);
+        assert_eq!(
+            codec.decode(&[]),
+            Err(CodecError::IndexOutOfRange(Piece::P, 0))
+        );
+        let mut pieces = [0; NUM_PIECES];
+        pieces[Piece::O as usize] = 0b11111;
+        assert_eq!(
+            codec.encode(&pieces),
+            Err(CodecError::NotAPlacement(Piece::P))
+        );
+    }
+
+    #[test]
+    fn rank_unrank() {
+        let expected = DefaultSolver::new(20, 3).solve(0, false);
+        for (rows, cols) in [(20, 3), (3, 20)] {
+            let ranking = Ranking::new(rows, cols, 0);
+            assert_eq!(ranking.count(), 8);
+            let solutions = (0..ranking.count())
+                .map(|r| ranking.unrank(r).unwrap())
+                .collect::<Vec<_>>();
+            let solutions_20x3 = solutions
+                .iter()
+                .map(|s| s.map(|b| if rows == 3 { transpose(3, 20, b) } else { b }))
+                .collect::<BTreeSet<_>>();
+            assert_eq!(solutions_20x3, expected.iter().copied().collect());
+            for (r, s) in solutions.iter().enumerate() {
+                assert_eq!(ranking.rank(s), Some(r as u64));
+            }
+            assert_eq!(ranking.unrank(ranking.count()), None);
+            assert_eq!(ranking.rank(&[0; NUM_PIECES]), None);
+        }
+
+        // numbers are part of the format
+        let ranking = Ranking::new(6, 10, 0);
+        assert_eq!(ranking.count(), 9356);
+        let rows: Vec<String> = Board::new(6, 10, &[])
+            .unwrap()
+            .grid(&ranking.unrank(1234).unwrap())
+            .into();
+        assert_eq!(rows, GRID_6X10);
+        for r in (0..ranking.count()).step_by(97) {
+            assert_eq!(ranking.rank(&ranking.unrank(r).unwrap()), Some(r));
+        }
+    }
+}
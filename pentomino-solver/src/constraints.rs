@@ -0,0 +1,413 @@
+//! Extra conditions on the solutions of a search.
+//!
+//! A `Constraint` states a condition on one or two pieces, in the coordinates
+//! of the board as given to the solver. Pieces use the letters of this crate,
+//! which name F, I, L and N of the other common scheme R, O, Q and S.
+//!
+//! Constraints on a single piece only allow some of its placements, so the
+//! solvers drop the others from their tables before searching. Constraints
+//! between pieces are checked when a solution is complete.
+//!
+//! A `PlacementFilter` gives the placements directly, as lists of allowed or
+//! denied placements and orientations of each piece and cells that a given
+//! piece must cover.
+
+use crate::sampling::transpose;
+use crate::shapes::{calculate_shapes, orientation};
+use crate::solvers::SolutionStore;
+use crate::{Bitboard, Piece, NUM_PIECES};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Constraint {
+    /// The piece covers a cell on the edge of the board.
+    TouchesBorder(Piece),
+    /// The piece covers no cell on the edge of the board.
+    AvoidsBorder(Piece),
+    /// The piece is taller than it is wide.
+    Vertical(Piece),
+    /// The piece is wider than it is tall.
+    Horizontal(Piece),
+    /// The piece covers the cell `(x, y)`, which no other piece does.
+    Covers(Piece, usize, usize),
+    /// The piece lies inside the cells of the bitboard.
+    Within(Piece, Bitboard),
+    /// The pieces share an edge.
+    Adjacent(Piece, Piece),
+    /// The pieces share no edge.
+    NotAdjacent(Piece, Piece),
+}
+
+/// Lists of the placements each piece may take, in the coordinates of the
+/// board. A placement passes if it is on the piece's allow-lists, where it
+/// has any, and on none of its deny-lists.
+#[derive(Debug, Clone, Default)]
+pub struct PlacementFilter {
+    allowed: [Option<Vec<Bitboard>>; NUM_PIECES],
+    denied: [Vec<Bitboard>; NUM_PIECES],
+    allowed_orientations: [Option<Vec<usize>>; NUM_PIECES],
+    denied_orientations: [Vec<usize>; NUM_PIECES],
+    forced: Vec<(Piece, usize, usize)>,
+}
+
+impl PlacementFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn allow_placements(mut self, piece: Piece, placements: &[Bitboard]) -> Self {
+        self.allowed[piece as usize]
+            .get_or_insert_with(Vec::new)
+            .extend_from_slice(placements);
+        self
+    }
+    pub fn deny_placements(mut self, piece: Piece, placements: &[Bitboard]) -> Self {
+        self.denied[piece as usize].extend_from_slice(placements);
+        self
+    }
+    /// Allows the orientations given as indices into `Piece::orientations`.
+    pub fn allow_orientations(mut self, piece: Piece, orientations: &[usize]) -> Self {
+        self.allowed_orientations[piece as usize]
+            .get_or_insert_with(Vec::new)
+            .extend_from_slice(orientations);
+        self
+    }
+    /// Denies the orientations given as indices into `Piece::orientations`.
+    pub fn deny_orientations(mut self, piece: Piece, orientations: &[usize]) -> Self {
+        self.denied_orientations[piece as usize].extend_from_slice(orientations);
+        self
+    }
+    /// The cell `(x, y)` must be covered by the piece, so it is denied to
+    /// all others.
+    pub fn force_cell(mut self, piece: Piece, x: usize, y: usize) -> Self {
+        self.forced.push((piece, x, y));
+        self
+    }
+    /// Whether piece `i` may be placed on `b` on a board `cols` wide.
+    pub fn allows(&self, cols: usize, i: usize, b: Bitboard) -> bool {
+        if self.allowed[i].as_ref().is_some_and(|v| !v.contains(&b)) || self.denied[i].contains(&b)
+        {
+            return false;
+        }
+        if self.allowed_orientations[i].is_some() || !self.denied_orientations[i].is_empty() {
+            let cells = (0..64)
+                .filter(|z| b & (1 << z) != 0)
+                .map(|z| (z % cols, z / cols))
+                .collect::<Vec<_>>();
+            let Some(k) = orientation(&calculate_shapes(), i, &cells) else {
+                return false;
+            };
+            if self.allowed_orientations[i]
+                .as_ref()
+                .is_some_and(|v| !v.contains(&k))
+                || self.denied_orientations[i].contains(&k)
+            {
+                return false;
+            }
+        }
+        self.forced.iter().all(|&(p, x, y)| {
+            let covered = x < cols && x + y * cols < 64 && b & (1 << (x + y * cols)) != 0;
+            covered == (i == p as usize)
+        })
+    }
+}
+
+pub trait ConstrainedSolver {
+    /// All solutions with the cells of `initial` filled that meet every
+    /// constraint, in the same layout as `Solver::solve` returns them.
+    fn solve_constrained(
+        &self,
+        initial: Bitboard,
+        constraints: &[Constraint],
+    ) -> Vec<[Bitboard; NUM_PIECES]>;
+    /// All solutions with the cells of `initial` filled whose placements all
+    /// pass `filter`, in the same layout as `Solver::solve` returns them.
+    fn solve_filtered(
+        &self,
+        initial: Bitboard,
+        filter: &PlacementFilter,
+    ) -> Vec<[Bitboard; NUM_PIECES]>;
+}
+
+impl Constraint {
+    /// Whether the solution on a `rows` x `cols` board meets the constraint.
+    pub fn is_satisfied(&self, rows: usize, cols: usize, pieces: &[Bitboard; NUM_PIECES]) -> bool {
+        let board = Board::new(rows, cols);
+        match *self {
+            Self::Adjacent(p, q) => board.expand(pieces[p as usize]) & pieces[q as usize] != 0,
+            Self::NotAdjacent(p, q) => board.expand(pieces[p as usize]) & pieces[q as usize] == 0,
+            _ => pieces
+                .iter()
+                .enumerate()
+                .all(|(i, &b)| self.allows(&board, i, b) != Some(false)),
+        }
+    }
+    /// Whether piece `i` may be placed on `b`, or `None` if that depends on
+    /// other pieces.
+    fn allows(&self, board: &Board, i: usize, b: Bitboard) -> Option<bool> {
+        let (w, h) = board.size(b);
+        match *self {
+            Self::TouchesBorder(p) => Some(i != p as usize || b & board.border != 0),
+            Self::AvoidsBorder(p) => Some(i != p as usize || b & board.border == 0),
+            Self::Vertical(p) => Some(i != p as usize || h > w),
+            Self::Horizontal(p) => Some(i != p as usize || w > h),
+            Self::Covers(p, x, y) => {
+                let covered =
+                    x < board.cols && y < board.rows && b & (1 << (x + y * board.cols)) != 0;
+                Some(covered == (i == p as usize))
+            }
+            Self::Within(p, region) => Some(i != p as usize || b & !region == 0),
+            Self::Adjacent(..) | Self::NotAdjacent(..) => None,
+        }
+    }
+}
+
+struct Board {
+    rows: usize,
+    cols: usize,
+    border: Bitboard,
+    not_first_col: Bitboard,
+    not_last_col: Bitboard,
+}
+
+impl Board {
+    fn new(rows: usize, cols: usize) -> Self {
+        let first_col = (0..rows).map(|y| 1 << (y * cols)).sum::<Bitboard>();
+        let last_col = first_col << (cols - 1);
+        let first_row = !0 >> (64 - cols);
+        let last_row = first_row << ((rows - 1) * cols);
+        Self {
+            rows,
+            cols,
+            border: first_col | last_col | first_row | last_row,
+            not_first_col: !first_col,
+            not_last_col: !last_col,
+        }
+    }
+    /// The cells of `b` and their neighbours.
+    fn expand(&self, b: Bitboard) -> Bitboard {
+        b | ((b << 1) & self.not_first_col)
+            | ((b >> 1) & self.not_last_col)
+            | (b << self.cols)
+            | (b >> self.cols)
+    }
+    /// Width and height of the cells of `b`.
+    fn size(&self, b: Bitboard) -> (usize, usize) {
+        let cells = (0..64).filter(|z| b & (1 << z) != 0);
+        let (xs, ys) = cells.fold(
+            ((usize::MAX, 0), (usize::MAX, 0)),
+            |((x0, x1), (y0, y1)), z| {
+                let (x, y) = (z % self.cols, z / self.cols);
+                ((x0.min(x), x1.max(x)), (y0.min(y), y1.max(y)))
+            },
+        );
+        (
+            (xs.1 + 1).saturating_sub(xs.0),
+            (ys.1 + 1).saturating_sub(ys.0),
+        )
+    }
+}
+
+/// Constraints compiled for a solver, whose board may be the transpose of
+/// the one they are stated on.
+pub(crate) struct Constraints<'a> {
+    rows: usize,
+    cols: usize,
+    transposed: bool,
+    board: Board,
+    list: &'a [Constraint],
+    filter: Option<&'a PlacementFilter>,
+}
+
+impl<'a> Constraints<'a> {
+    /// Constraints on a `rows` x `cols` board, for a solver that works on
+    /// its transpose if `transposed`.
+    pub(crate) fn new(rows: usize, cols: usize, transposed: bool, list: &'a [Constraint]) -> Self {
+        Self {
+            rows,
+            cols,
+            transposed,
+            board: Board::new(rows, cols),
+            list,
+            filter: None,
+        }
+    }
+    /// The placements that pass `filter`, on a board as for `new`.
+    pub(crate) fn filter(
+        rows: usize,
+        cols: usize,
+        transposed: bool,
+        filter: &'a PlacementFilter,
+    ) -> Self {
+        Self {
+            filter: Some(filter),
+            ..Self::new(rows, cols, transposed, &[])
+        }
+    }
+    fn to_board(&self, b: Bitboard) -> Bitboard {
+        if self.transposed {
+            transpose(self.cols, self.rows, b)
+        } else {
+            b
+        }
+    }
+    /// Whether the single-piece constraints allow placing piece `i` on `b`,
+    /// in the solver's layout.
+    pub(crate) fn allows(&self, i: usize, b: Bitboard) -> bool {
+        let b = self.to_board(b);
+        self.filter.is_none_or(|f| f.allows(self.cols, i, b))
+            && self
+                .list
+                .iter()
+                .all(|c| c.allows(&self.board, i, b) != Some(false))
+    }
+    /// Whether a solution built from allowed placements meets the remaining
+    /// constraints.
+    fn check(&self, pieces: &[Bitboard; NUM_PIECES]) -> bool {
+        let pieces = pieces.map(|b| self.to_board(b));
+        self.list
+            .iter()
+            .filter(|c| matches!(c, Constraint::Adjacent(..) | Constraint::NotAdjacent(..)))
+            .all(|c| c.is_satisfied(self.rows, self.cols, &pieces))
+    }
+}
+
+/// Keeps the solutions that meet the constraints between pieces.
+pub(crate) struct ConstrainedStore<'a> {
+    pub(crate) constraints: Constraints<'a>,
+    pub(crate) solutions: Vec<[Bitboard; NUM_PIECES]>,
+}
+
+impl SolutionStore for ConstrainedStore<'_> {
+    fn add_solution(&mut self, pieces: &[Bitboard; NUM_PIECES]) {
+        if self.constraints.check(pieces) {
+            self.solutions.push(*pieces);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Grid;
+    use crate::solvers::{DefaultSolver, OptimizedSolver, OptimizedSolverType};
+    use crate::Solver;
+    use std::collections::BTreeSet;
+
+    fn cases() -> Vec<Vec<Constraint>> {
+        vec![
+            vec![],
+            vec![Constraint::AvoidsBorder(Piece::X)],
+            vec![Constraint::TouchesBorder(Piece::X)],
+            vec![Constraint::Vertical(Piece::O)],
+            vec![Constraint::Horizontal(Piece::O)],
+            vec![Constraint::Adjacent(Piece::R, Piece::P)],
+            vec![
+                Constraint::NotAdjacent(Piece::R, Piece::P),
+                Constraint::Covers(Piece::U, 0, 0),
+            ],
+            vec![Constraint::Within(Piece::Z, (1 << 30) - 1)],
+        ]
+    }
+
+    /// Solutions in board layout, as the solver shows them.
+    fn on_board(
+        solver: &dyn Solver,
+        solutions: &[[Bitboard; NUM_PIECES]],
+    ) -> BTreeSet<[Bitboard; NUM_PIECES]> {
+        solutions
+            .iter()
+            .map(|s| Grid(solver.represent_solution(s)).solution().unwrap().1)
+            .collect()
+    }
+
+    #[test]
+    fn matches_filtered_solutions() {
+        let center = (1 << 27) | (1 << 28) | (1 << 35) | (1 << 36);
+        for (rows, cols, initial) in [(20, 3, 0), (3, 20, 0), (8, 8, center)] {
+            let optimized = OptimizedSolver::new(rows, cols, OptimizedSolverType::SmallTable);
+            let all = on_board(&optimized, &optimized.solve(initial, false));
+            let default = DefaultSolver::new(rows, cols);
+            for constraints in cases() {
+                let expected = all
+                    .iter()
+                    .filter(|s| constraints.iter().all(|c| c.is_satisfied(rows, cols, s)))
+                    .copied()
+                    .collect::<BTreeSet<_>>();
+                let found = optimized.solve_constrained(initial, &constraints);
+                assert_eq!(found.len(), expected.len(), "{constraints:?}");
+                assert_eq!(on_board(&optimized, &found), expected, "{constraints:?}");
+                if rows > cols {
+                    let found = default.solve_constrained(initial, &constraints);
+                    assert_eq!(found.len(), expected.len(), "{constraints:?}");
+                    assert_eq!(found.into_iter().collect::<BTreeSet<_>>(), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn placement_filters() {
+        assert_eq!(Piece::O.orientations().len(), 2);
+        assert_eq!(Piece::R.orientations().len(), 8);
+        assert_eq!(
+            Piece::X.orientations(),
+            [[(1, 0), (0, 1), (1, 1), (2, 1), (1, 2)]]
+        );
+
+        let center = (1 << 27) | (1 << 28) | (1 << 35) | (1 << 36);
+        for (rows, cols, initial) in [(20, 3, 0), (3, 20, 0), (8, 8, center)] {
+            let optimized = OptimizedSolver::new(rows, cols, OptimizedSolverType::SmallTable);
+            let all = on_board(&optimized, &optimized.solve(initial, false));
+            let default = DefaultSolver::new(rows, cols);
+            let first = all.iter().next().unwrap();
+            let filters = [
+                PlacementFilter::new(),
+                PlacementFilter::new().allow_placements(Piece::X, &[first[Piece::X as usize]]),
+                PlacementFilter::new().deny_placements(Piece::U, &[first[Piece::U as usize]]),
+                PlacementFilter::new().allow_orientations(Piece::R, &[0, 5]),
+                PlacementFilter::new()
+                    .deny_orientations(Piece::O, &[0])
+                    .deny_orientations(Piece::W, &[1, 2]),
+                PlacementFilter::new().force_cell(Piece::Z, 0, 0),
+            ];
+            for filter in filters {
+                let expected = all
+                    .iter()
+                    .filter(|s| (0..NUM_PIECES).all(|i| filter.allows(cols, i, s[i])))
+                    .copied()
+                    .collect::<BTreeSet<_>>();
+                let found = optimized.solve_filtered(initial, &filter);
+                assert_eq!(found.len(), expected.len(), "{filter:?}");
+                assert_eq!(on_board(&optimized, &found), expected, "{filter:?}");
+                if rows > cols {
+                    let found = default.solve_filtered(initial, &filter);
+                    assert_eq!(found.len(), expected.len(), "{filter:?}");
+                    assert_eq!(found.into_iter().collect::<BTreeSet<_>>(), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn single_pieces() {
+        let o = (0..5).map(|y| 1 << (y * 4)).sum::<Bitboard>();
+        let mut pieces = [0; NUM_PIECES];
+        pieces[Piece::O as usize] = o;
+        assert_eq!(Board::new(5, 4).size(o), (1, 5));
+        assert!(Constraint::Vertical(Piece::O).is_satisfied(5, 4, &pieces));
+        assert!(!Constraint::Horizontal(Piece::O).is_satisfied(5, 4, &pieces));
+        assert!(Constraint::TouchesBorder(Piece::O).is_satisfied(5, 4, &pieces));
+        assert!(Constraint::Covers(Piece::O, 0, 4).is_satisfied(5, 4, &pieces));
+        assert!(!Constraint::Covers(Piece::P, 0, 4).is_satisfied(5, 4, &pieces));
+        assert!(!Constraint::Covers(Piece::O, 0, 5).is_satisfied(5, 4, &pieces));
+
+        let (rows, cols) = (3, 4);
+
+        let x = [1, 4, 5, 6, 9].iter().map(|&z| 1 << z).sum::<Bitboard>() << 1;
+        let board = Board::new(rows, cols);
+        assert_eq!(board.size(x), (3, 3));
+        assert_eq!(board.expand(1 << 3), (1 << 3) | (1 << 2) | (1 << 7));
+        pieces[Piece::X as usize] = x;
+        assert!(!Constraint::Vertical(Piece::X).is_satisfied(rows, cols, &pieces));
+        assert!(!Constraint::AvoidsBorder(Piece::X).is_satisfied(rows, cols, &pieces));
+    }
+}
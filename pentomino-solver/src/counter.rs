@@ -0,0 +1,119 @@
+//! Counting solutions by dynamic programming over the filled frontier.
+//!
+//! Cells are filled in order along the board's long side, so once the first
+//! empty cell is known, everything before it is filled and the rest of the
+//! occupied cells lie within the next few lines across the short side. That
+//! frontier profile together with the set of used pieces determines the
+//! number of ways to finish the board, and is memoised. On long thin boards
+//! the number of distinct frontiers is small, while the backtracking solvers
+//! revisit the same ones many times.
+
+use crate::shapes::calculate_shapes;
+use crate::{Bitboard, NUM_PIECES};
+use std::collections::HashMap;
+
+pub struct FrontierCounter {
+    rows: usize,
+    cols: usize,
+    /// Placements indexed by their first cell in scan order and piece.
+    table: Vec<[Vec<Bitboard>; NUM_PIECES]>,
+}
+
+impl FrontierCounter {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        assert!(rows * cols <= 64);
+        let mut table = vec![std::array::from_fn(|_| Vec::new()); rows * cols];
+        for (n, shape) in calculate_shapes().iter().enumerate() {
+            for s in shape {
+                if s.iter().any(|&(x, y)| x >= cols || y >= rows) {
+                    continue;
+                }
+                let (w, h) = s
+                    .iter()
+                    .fold((0, 0), |(xmax, ymax), &(x, y)| (xmax.max(x), ymax.max(y)));
+                for y in 0..rows - h {
+                    for x in 0..cols - w {
+                        let u = s
+                            .iter()
+                            .map(|&(dx, dy)| 1 << Self::scan_index(rows, cols, x + dx, y + dy))
+                            .sum::<Bitboard>();
+                        table[u.trailing_zeros() as usize][n].push(u);
+                    }
+                }
+            }
+        }
+        Self { rows, cols, table }
+    }
+    /// Position of cell `(x, y)` in the order the counter fills the board.
+    fn scan_index(rows: usize, cols: usize, x: usize, y: usize) -> usize {
+        if rows <= cols {
+            y + x * rows
+        } else {
+            x + y * cols
+        }
+    }
+    /// Number of solutions on the board with the cells of `initial` (numbered
+    /// `x + y * cols`, as for the solvers) already filled.
+    pub fn count(&self, initial: Bitboard) -> u64 {
+        let cells = self.rows * self.cols;
+        if cells != initial.count_ones() as usize + 5 * NUM_PIECES {
+            return 0;
+        }
+        let mut current = 0;
+        for z in 0..cells {
+            if initial & (1 << z) != 0 {
+                current |=
+                    1 << Self::scan_index(self.rows, self.cols, z % self.cols, z / self.cols);
+            }
+        }
+        self.search(current, 0, &mut HashMap::new())
+    }
+    fn search(
+        &self,
+        current: Bitboard,
+        used: usize,
+        memo: &mut HashMap<(Bitboard, usize), u64>,
+    ) -> u64 {
+        if used == (1 << NUM_PIECES) - 1 {
+            return 1;
+        }
+        if let Some(&n) = memo.get(&(current, used)) {
+            return n;
+        }
+        let target = current.trailing_ones() as usize;
+        let mut n = 0;
+        for (i, candidates) in self.table[target].iter().enumerate() {
+            if used & (1 << i) == 0 {
+                for &b in candidates {
+                    if current & b == 0 {
+                        n += self.search(current | b, used | (1 << i), memo);
+                    }
+                }
+            }
+        }
+        memo.insert((current, used), n);
+        n
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solvers::DefaultSolver;
+    use crate::Solver;
+
+    #[test]
+    fn matches_default_solver() {
+        for (rows, cols, initial) in [
+            (20, 3, 0),
+            // two holes in a corner and one halfway along a 3x21 board
+            (21, 3, (1 << 0) | (1 << 1) | (1 << 36)),
+            (15, 4, 0),
+        ] {
+            let expected = DefaultSolver::new(rows, cols).solve(initial, false).len() as u64;
+            assert_eq!(FrontierCounter::new(rows, cols).count(initial), expected);
+        }
+        assert_eq!(FrontierCounter::new(3, 20).count(0), 8);
+        assert_eq!(FrontierCounter::new(6, 10).count(0), 9356);
+    }
+}
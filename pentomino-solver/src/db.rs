@@ -0,0 +1,444 @@
+//! Single-file stores of solutions.
+//!
+//! A database file starts with the magic bytes `PNDB`, a format version, the
+//! board size, a flag byte (bit 0 set if only one solution of every symmetry
+//! class was stored), the filled cells as a little-endian `u64` and the
+//! number of solutions as another. Then follow the solutions, each encoded by
+//! `SolutionCodec` in the same number of bytes.
+//!
+//! Queries constrain the placements of single pieces. They are checked on the
+//! encoded solutions, which are read from the file and decoded only as the
+//! results are iterated.
+
+use crate::board::{BoardError, Grid};
+use crate::codec::{CodecError, SolutionCodec};
+use crate::{Bitboard, Piece, Solver, NUM_PIECES};
+use derive_more::Display;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 4] = b"PNDB";
+const VERSION: u8 = 1;
+const HEADER_LEN: u64 = 24;
+
+#[derive(Debug, Display)]
+pub enum DbError {
+    #[display(fmt = "{}", _0)]
+    Io(std::io::Error),
+    #[display(fmt = "not a solution database")]
+    InvalidMagic,
+    #[display(fmt = "unsupported database format version {}", _0)]
+    UnsupportedVersion(u8),
+    #[display(fmt = "solution database is truncated")]
+    Truncated,
+    #[display(fmt = "solution database is corrupted")]
+    Corrupted,
+    #[display(fmt = "invalid solution: {}", _0)]
+    InvalidSolution(CodecError),
+    #[display(fmt = "solution is not on a {}x{} board", _0, _1)]
+    BoardMismatch(usize, usize),
+    #[display(fmt = "{}", _0)]
+    InvalidBoard(BoardError),
+}
+
+impl std::error::Error for DbError {}
+
+impl From<std::io::Error> for DbError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Writes a database file, solution by solution.
+pub struct DbWriter {
+    file: BufWriter<File>,
+    codec: SolutionCodec,
+    count: u64,
+}
+
+impl DbWriter {
+    /// Creates the file for solutions of the board with the cells of
+    /// `initial` filled.
+    pub fn create(
+        path: impl AsRef<Path>,
+        rows: usize,
+        cols: usize,
+        initial: Bitboard,
+        unique: bool,
+    ) -> Result<Self, DbError> {
+        if rows == 0 || cols == 0 || rows * cols > 64 {
+            return Err(DbError::InvalidBoard(BoardError::InvalidSize(rows, cols)));
+        }
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&[VERSION, rows as u8, cols as u8, unique as u8])?;
+        file.write_all(&initial.to_le_bytes())?;
+        file.write_all(&0u64.to_le_bytes())?;
+        Ok(Self {
+            file,
+            codec: SolutionCodec::new(rows, cols),
+            count: 0,
+        })
+    }
+    pub fn push(&mut self, pieces: &[Bitboard; NUM_PIECES]) -> Result<(), DbError> {
+        let bytes = self
+            .codec
+            .encode(pieces)
+            .map_err(DbError::InvalidSolution)?;
+        self.file.write_all(&bytes)?;
+        self.count += 1;
+        Ok(())
+    }
+    /// Writes the number of solutions into the header and closes the file.
+    pub fn finish(mut self) -> Result<u64, DbError> {
+        self.file.seek(SeekFrom::Start(HEADER_LEN - 8))?;
+        self.file.write_all(&self.count.to_le_bytes())?;
+        self.file.flush()?;
+        Ok(self.count)
+    }
+}
+
+/// Constraints on the placements of pieces, all of which a solution must
+/// meet to match.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    constraints: Vec<(Piece, Constraint)>,
+}
+
+#[derive(Debug, Clone)]
+enum Constraint {
+    Within(Bitboard),
+    Touching(Bitboard),
+    TouchingBorder,
+    Is(Bitboard),
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// The piece lies inside `region`.
+    pub fn within(mut self, piece: Piece, region: Bitboard) -> Self {
+        self.constraints.push((piece, Constraint::Within(region)));
+        self
+    }
+    /// The piece covers at least one of `cells`.
+    pub fn touching(mut self, piece: Piece, cells: Bitboard) -> Self {
+        self.constraints.push((piece, Constraint::Touching(cells)));
+        self
+    }
+    /// The piece covers a cell on the edge of the board.
+    pub fn touching_border(mut self, piece: Piece) -> Self {
+        self.constraints.push((piece, Constraint::TouchingBorder));
+        self
+    }
+    /// The piece covers exactly `cells`.
+    pub fn placement(mut self, piece: Piece, cells: Bitboard) -> Self {
+        self.constraints.push((piece, Constraint::Is(cells)));
+        self
+    }
+    /// For every constrained piece, which of its placements in `codec` are
+    /// allowed.
+    fn compile(&self, rows: usize, cols: usize, codec: &SolutionCodec) -> Vec<(usize, Vec<bool>)> {
+        let border = (0..rows * cols)
+            .filter(|z| {
+                z % cols == 0 || z % cols == cols - 1 || z / cols == 0 || z / cols == rows - 1
+            })
+            .fold(0, |acc, z| acc | (1 << z));
+        let mut ret = Vec::<(usize, Vec<bool>)>::new();
+        for (piece, constraint) in &self.constraints {
+            let i = *piece as usize;
+            let placements = codec.placements(i);
+            let k = ret.iter().position(|(j, _)| *j == i).unwrap_or_else(|| {
+                ret.push((i, vec![true; placements.len()]));
+                ret.len() - 1
+            });
+            for (allowed, &b) in ret[k].1.iter_mut().zip(placements) {
+                *allowed &= match *constraint {
+                    Constraint::Within(region) => b & !region == 0,
+                    Constraint::Touching(cells) => b & cells != 0,
+                    Constraint::TouchingBorder => b & border != 0,
+                    Constraint::Is(cells) => b == cells,
+                };
+            }
+        }
+        ret
+    }
+}
+
+pub struct SolutionDb {
+    path: PathBuf,
+    rows: usize,
+    cols: usize,
+    initial: Bitboard,
+    unique: bool,
+    len: u64,
+    codec: SolutionCodec,
+}
+
+impl SolutionDb {
+    /// Writes all solutions that `solver` finds for the board to a new file,
+    /// and opens it.
+    pub fn write(
+        path: impl AsRef<Path>,
+        solver: &dyn Solver,
+        rows: usize,
+        cols: usize,
+        initial: Bitboard,
+        unique: bool,
+    ) -> Result<Self, DbError> {
+        let mut writer = DbWriter::create(&path, rows, cols, initial, unique)?;
+        for solution in solver.solve(initial, unique) {
+            // as the solver shows it, since some don't keep the board's layout
+            let grid = Grid(solver.represent_solution(&solution));
+            match grid.solution() {
+                Ok((board, pieces)) if (board.rows(), board.cols()) == (rows, cols) => {
+                    writer.push(&pieces)?
+                }
+                _ => return Err(DbError::BoardMismatch(rows, cols)),
+            }
+        }
+        writer.finish()?;
+        Self::open(path)
+    }
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, DbError> {
+        let mut file = File::open(&path)?;
+        let mut header = [0; HEADER_LEN as usize];
+        file.read_exact(&mut header).map_err(|e| match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => DbError::Truncated,
+            _ => DbError::Io(e),
+        })?;
+        if &header[..4] != MAGIC {
+            return Err(DbError::InvalidMagic);
+        }
+        if header[4] != VERSION {
+            return Err(DbError::UnsupportedVersion(header[4]));
+        }
+        let (rows, cols) = (header[5] as usize, header[6] as usize);
+        if rows == 0 || cols == 0 || rows * cols > 64 || header[7] > 1 {
+            return Err(DbError::Corrupted);
+        }
+        let codec = SolutionCodec::new(rows, cols);
+        let len = u64::from_le_bytes(header[16..24].try_into().unwrap());
+        let size = len
+            .checked_mul(codec.encoded_len() as u64)
+            .and_then(|n| n.checked_add(HEADER_LEN))
+            .ok_or(DbError::Corrupted)?;
+        match file.metadata()?.len() {
+            n if n < size => return Err(DbError::Truncated),
+            n if n > size => return Err(DbError::Corrupted),
+            _ => {}
+        }
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            rows,
+            cols,
+            initial: u64::from_le_bytes(header[8..16].try_into().unwrap()),
+            unique: header[7] == 1,
+            len,
+            codec,
+        })
+    }
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+    pub fn initial(&self) -> Bitboard {
+        self.initial
+    }
+    /// Whether only one solution of every symmetry class was stored.
+    pub fn unique(&self) -> bool {
+        self.unique
+    }
+    /// Number of stored solutions.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// All stored solutions, in the order they were written.
+    pub fn iter(&self) -> Result<Solutions<'_>, DbError> {
+        self.query(&Query::new())
+    }
+    /// The stored solutions that match `query`, in the order they were
+    /// written.
+    pub fn query(&self, query: &Query) -> Result<Solutions<'_>, DbError> {
+        let mut file = BufReader::new(File::open(&self.path)?);
+        file.seek(SeekFrom::Start(HEADER_LEN))?;
+        Ok(Solutions {
+            file,
+            codec: &self.codec,
+            filters: query.compile(self.rows, self.cols, &self.codec),
+            remaining: self.len,
+            record: vec![0; self.codec.encoded_len()],
+        })
+    }
+}
+
+/// Iterator over the solutions of a `SolutionDb` that match a query.
+pub struct Solutions<'a> {
+    file: BufReader<File>,
+    codec: &'a SolutionCodec,
+    filters: Vec<(usize, Vec<bool>)>,
+    remaining: u64,
+    record: Vec<u8>,
+}
+
+impl Iterator for Solutions<'_> {
+    type Item = Result<[Bitboard; NUM_PIECES], DbError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.remaining > 0 {
+            self.remaining -= 1;
+            if let Err(e) = self.file.read_exact(&mut self.record) {
+                self.remaining = 0;
+                return Some(Err(e.into()));
+            }
+            let matches = self.filters.iter().all(|(i, allowed)| {
+                allowed
+                    .get(self.codec.index(&self.record, *i))
+                    .is_some_and(|&a| a)
+            });
+            if matches {
+                return Some(
+                    self.codec
+                        .decode(&self.record)
+                        .map_err(|_| DbError::Corrupted),
+                );
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solvers::{DefaultSolver, OptimizedSolver, OptimizedSolverType};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("pentomino-{}-{name}.db", std::process::id()))
+    }
+
+    #[test]
+    fn write_and_query() {
+        let path = temp_path("query");
+        let solutions = DefaultSolver::new(20, 3).solve(0, false);
+        let db = SolutionDb::write(&path, &DefaultSolver::new(20, 3), 20, 3, 0, false).unwrap();
+        assert_eq!(
+            (db.rows(), db.cols(), db.initial(), db.unique()),
+            (20, 3, 0, false)
+        );
+        assert_eq!(db.len(), 8);
+        let stored = db.iter().unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(stored, solutions);
+
+        let x = Piece::X as usize;
+        let top = (1 << 30) - 1;
+        let query = |q: Query| {
+            db.query(&q)
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        };
+        let expected = |f: &dyn Fn(&[Bitboard; NUM_PIECES]) -> bool| {
+            solutions
+                .iter()
+                .filter(|s| f(s))
+                .copied()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            query(Query::new().within(Piece::X, top)),
+            expected(&|s| s[x] & !top == 0)
+        );
+        let border = (0..60)
+            .filter(|z| z % 3 != 1 || z / 3 == 0 || z / 3 == 19)
+            .fold(0, |acc, z| acc | (1 << z));
+        for piece in [Piece::O, Piece::X, Piece::T] {
+            let i = piece as usize;
+            assert_eq!(
+                query(Query::new().touching_border(piece)),
+                expected(&|s| s[i] & border != 0)
+            );
+        }
+        let p = solutions[3][Piece::P as usize];
+        let found = query(Query::new().placement(Piece::P, p).touching(Piece::X, top));
+        assert_eq!(
+            found,
+            expected(&|s| s[Piece::P as usize] == p && s[x] & top != 0)
+        );
+        assert!(query(Query::new().within(Piece::X, 0)).is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn solver_layouts_and_invalid_files() {
+        // the optimized solvers work on a transposed board internally
+        let path = temp_path("invalid");
+        let solver = OptimizedSolver::new(3, 20, OptimizedSolverType::SmallTable);
+        let db = SolutionDb::write(&path, &solver, 3, 20, 0, true).unwrap();
+        assert_eq!(db.len(), 2);
+        assert!(db.unique());
+        let solver = DefaultSolver::new(3, 20);
+        for s in db.iter().unwrap() {
+            let grid = Grid(solver.represent_solution(&s.unwrap()));
+            assert_eq!(grid.0.len(), 3);
+        }
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(matches!(SolutionDb::open(&path), Err(DbError::Truncated)));
+        std::fs::write(&path, &bytes[..10]).unwrap();
+        assert!(matches!(SolutionDb::open(&path), Err(DbError::Truncated)));
+        let mut corrupted = bytes.clone();
+        corrupted[4] = 2;
+        std::fs::write(&path, &corrupted).unwrap();
+        assert!(matches!(
+            SolutionDb::open(&path),
+            Err(DbError::UnsupportedVersion(2))
+        ));
+        corrupted[0] = b'X';
+        std::fs::write(&path, &corrupted).unwrap();
+        assert!(matches!(
+            SolutionDb::open(&path),
+            Err(DbError::InvalidMagic)
+        ));
+        // a count whose size overflows u64
+        let mut corrupted = bytes.clone();
+        corrupted[16..24].copy_from_slice(&u64::MAX.to_le_bytes());
+        std::fs::write(&path, &corrupted).unwrap();
+        assert!(matches!(SolutionDb::open(&path), Err(DbError::Corrupted)));
+        std::fs::remove_file(&path).unwrap();
+
+        for (rows, cols) in [(9, 8), (0, 5), (65, 1)] {
+            assert!(matches!(
+                DbWriter::create(&path, rows, cols, 0, false),
+                Err(DbError::InvalidBoard(BoardError::InvalidSize(r, c))) if (r, c) == (rows, cols)
+            ));
+        }
+        assert!(!path.exists());
+
+        let mut writer = DbWriter::create(&path, 3, 20, 0, false).unwrap();
+        assert!(matches!(
+            writer.push(&[0; NUM_PIECES]),
+            Err(DbError::InvalidSolution(CodecError::NotAPlacement(
+                Piece::O
+            )))
+        ));
+        assert_eq!(writer.finish().unwrap(), 0);
+        assert!(SolutionDb::open(&path).unwrap().is_empty());
+        std::fs::remove_file(&path).unwrap();
+
+        // a board too narrow for some pieces has no solutions to store
+        let db = SolutionDb::write(&path, &DefaultSolver::new(2, 30), 2, 30, 0, false).unwrap();
+        assert_eq!((db.rows(), db.cols(), db.len()), (2, 30, 0));
+        assert!(SolutionDb::open(&path).unwrap().is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
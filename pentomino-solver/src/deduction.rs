@@ -0,0 +1,272 @@
+//! Placements forced by logic rather than search.
+//!
+//! A `Propagator` starts from a partial solution and keeps, for every piece
+//! not yet placed, the placements that still fit: those on empty cells that
+//! leave no empty region whose size is not a multiple of five. From these it
+//! repeatedly forces a placement when it is the only one left of its piece,
+//! or the only one covering some empty cell, until neither rule applies.
+//! Forced placements are in every solution that extends the partial one.
+//!
+//! The rules assume that the pieces left exactly cover the empty cells, as on
+//! a board of 60 empty cells. Elsewhere a cell may stay empty, so both rules
+//! fail with `Obstruction::CellCount` instead.
+
+use crate::diagnostics::{full, regions, Obstruction};
+use crate::solvers::DefaultSolver;
+use crate::{Bitboard, Piece, NUM_PIECES};
+use derive_more::Display;
+use num_traits::FromPrimitive;
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    /// No other placement covers the cell `(x, y)`.
+    #[display(fmt = "only placement covering ({}, {})", _0, _1)]
+    OnlyCover(usize, usize),
+    /// No other placement of the piece is left.
+    #[display(fmt = "only placement of the piece")]
+    OnlyPlacement,
+}
+
+/// A placement forced by a rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deduction {
+    pub piece: Piece,
+    pub placement: Bitboard,
+    pub rule: Rule,
+}
+
+pub struct Propagator {
+    rows: usize,
+    cols: usize,
+    placements: [Vec<Bitboard>; NUM_PIECES],
+}
+
+impl Propagator {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        let mut placements: [Vec<Bitboard>; NUM_PIECES] = Default::default();
+        for (i, b) in DefaultSolver::new(rows, cols).placements() {
+            placements[i].push(b);
+        }
+        Self {
+            rows,
+            cols,
+            placements,
+        }
+    }
+    /// The empty cells of the partial solution `pieces`, if the pieces not
+    /// placed yet cover exactly as many cells.
+    fn empty(
+        &self,
+        initial: Bitboard,
+        pieces: &[Bitboard; NUM_PIECES],
+    ) -> Result<Bitboard, Obstruction> {
+        let full = full(self.rows, self.cols);
+        let empty = full & !pieces.iter().fold(initial, |acc, b| acc | b);
+        let left = pieces.iter().filter(|&&b| b == 0).count();
+        if empty.count_ones() as usize != 5 * left {
+            return Err(Obstruction::CellCount(
+                (full & !initial).count_ones() as usize
+            ));
+        }
+        Ok(empty)
+    }
+    /// The placements still possible for every piece of `pieces` that is not
+    /// placed yet, which is one without cells. Placed pieces have none.
+    pub fn candidates(
+        &self,
+        initial: Bitboard,
+        pieces: &[Bitboard; NUM_PIECES],
+    ) -> Result<[Vec<Bitboard>; NUM_PIECES], Obstruction> {
+        let empty = self.empty(initial, pieces)?;
+        Ok(std::array::from_fn(|i| {
+            if pieces[i] != 0 {
+                return Vec::new();
+            }
+            self.placements[i]
+                .iter()
+                .copied()
+                .filter(|&b| b & !empty == 0 && !self.isolates(empty & !b))
+                .collect()
+        }))
+    }
+    /// Forces placements in the partial solution `pieces` on the board with
+    /// the cells of `initial` filled, in the order they were found. Fails if
+    /// some piece or cell is left without a placement, as the partial
+    /// solution then has no completion, or if the pieces left don't cover
+    /// exactly the empty cells.
+    pub fn propagate(
+        &self,
+        initial: Bitboard,
+        pieces: &[Bitboard; NUM_PIECES],
+    ) -> Result<Vec<Deduction>, Obstruction> {
+        let mut pieces = *pieces;
+        let mut ret = Vec::new();
+        while let Some((i, b, rule)) = self.step(initial, &pieces)? {
+            pieces[i] = b;
+            ret.push(Deduction {
+                piece: Piece::from_usize(i).unwrap(),
+                placement: b,
+                rule,
+            });
+        }
+        Ok(ret)
+    }
+    /// The next forced placement as `(piece, placement, rule)`, if any.
+    fn step(
+        &self,
+        initial: Bitboard,
+        pieces: &[Bitboard; NUM_PIECES],
+    ) -> Result<Option<(usize, Bitboard, Rule)>, Obstruction> {
+        let empty = self.empty(initial, pieces)?;
+        if let Some(&r) = regions(self.cols, empty)
+            .iter()
+            .find(|r| r.count_ones() % 5 != 0)
+        {
+            return Err(Obstruction::RegionSize(r));
+        }
+        let candidates = self.candidates(initial, pieces)?;
+        for (i, v) in candidates.iter().enumerate() {
+            if pieces[i] == 0 && v.is_empty() {
+                return Err(Obstruction::NoPlacement(Piece::from_usize(i).unwrap()));
+            }
+        }
+        for (i, v) in candidates.iter().enumerate() {
+            if let [b] = v[..] {
+                return Ok(Some((i, b, Rule::OnlyPlacement)));
+            }
+        }
+        for z in (0..64).filter(|z| empty & (1 << z) != 0) {
+            let mut covers = candidates
+                .iter()
+                .enumerate()
+                .flat_map(|(i, v)| v.iter().map(move |&b| (i, b)))
+                .filter(|(_, b)| b & (1 << z) != 0);
+            match (covers.next(), covers.next()) {
+                (None, _) => return Err(Obstruction::Uncoverable(1 << z)),
+                (Some((i, b)), None) => {
+                    return Ok(Some((i, b, Rule::OnlyCover(z % self.cols, z / self.cols))))
+                }
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+    /// Whether the `empty` cells have a region no set of pieces can fill.
+    fn isolates(&self, empty: Bitboard) -> bool {
+        regions(self.cols, empty)
+            .iter()
+            .any(|r| r.count_ones() % 5 != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::solution_6x10;
+
+    #[test]
+    fn forced_placements() {
+        let propagator = Propagator::new(6, 10);
+        let solution = solution_6x10();
+
+        // a single missing piece has a single placement left
+        let mut pieces = solution;
+        pieces[Piece::X as usize] = 0;
+        assert_eq!(
+            propagator.propagate(0, &pieces),
+            Ok(vec![Deduction {
+                piece: Piece::X,
+                placement: solution[Piece::X as usize],
+                rule: Rule::OnlyPlacement,
+            }])
+        );
+
+        // forced placements are in every completion, so in the solution
+        for removed in [0b1111, 0b1010_1010_1010, 0b1111_0000_0000, 0b11_1111] {
+            let mut pieces = solution;
+            for (i, b) in pieces.iter_mut().enumerate() {
+                if removed & (1 << i) != 0 {
+                    *b = 0;
+                }
+            }
+            for d in propagator.propagate(0, &pieces).unwrap() {
+                assert_eq!(solution[d.piece as usize], d.placement, "{removed:b}");
+            }
+        }
+
+        let mut pieces = solution;
+        for p in [Piece::O, Piece::P, Piece::R, Piece::S] {
+            pieces[p as usize] = 0;
+        }
+        let deductions = propagator.propagate(0, &pieces).unwrap();
+        let rules = deductions
+            .iter()
+            .map(|d| (d.piece, d.rule))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rules,
+            [
+                (Piece::O, Rule::OnlyPlacement),
+                (Piece::P, Rule::OnlyCover(5, 0)),
+            ]
+        );
+        assert_eq!(
+            deductions[1].rule.to_string(),
+            "only placement covering (5, 0)"
+        );
+        // R and S fill the rest in two ways
+        pieces[Piece::O as usize] = deductions[0].placement;
+        pieces[Piece::P as usize] = deductions[1].placement;
+        let candidates = propagator.candidates(0, &pieces).unwrap();
+        assert_eq!(candidates[Piece::R as usize].len(), 2);
+        assert_eq!(candidates[Piece::S as usize].len(), 2);
+    }
+
+    #[test]
+    fn contradictions() {
+        let propagator = Propagator::new(6, 10);
+        let cells = |v: &[u32]| v.iter().map(|z| 1 << z).sum::<Bitboard>();
+
+        // X next to the corner cuts it off
+        let mut pieces = [0; NUM_PIECES];
+        pieces[Piece::X as usize] = cells(&[1, 10, 11, 12, 21]);
+        assert_eq!(
+            propagator.propagate(0, &pieces),
+            Err(Obstruction::RegionSize(1))
+        );
+
+        // every placement covering (9, 0) cuts off a region
+        let mut pieces = [0; NUM_PIECES];
+        pieces[Piece::X as usize] = cells(&[2, 11, 12, 13, 22]);
+        pieces[Piece::O as usize] = cells(&[8, 18, 28, 38, 48]);
+        assert_eq!(
+            propagator.propagate(0, &pieces),
+            Err(Obstruction::Uncoverable(1 << 9))
+        );
+
+        let mut pieces = [0; NUM_PIECES];
+        pieces[Piece::X as usize] = cells(&[11, 20, 21, 22, 31]);
+        pieces[Piece::Y as usize] = cells(&[5, 14, 15, 16, 17]);
+        assert_eq!(
+            propagator.propagate(0, &pieces),
+            Err(Obstruction::NoPlacement(Piece::Z))
+        );
+
+        // a cell may stay empty when there are more than the pieces cover, so
+        // neither rule holds
+        let propagator = Propagator::new(7, 9);
+        let mut pieces = [0; NUM_PIECES];
+        pieces[Piece::X as usize] = cells(&[1, 9, 10, 11, 19]);
+        assert_eq!(
+            propagator.propagate(0, &pieces),
+            Err(Obstruction::CellCount(63))
+        );
+        assert_eq!(
+            propagator.candidates(0, &pieces),
+            Err(Obstruction::CellCount(63))
+        );
+        assert!(Propagator::new(6, 10)
+            .candidates(1, &[0; NUM_PIECES])
+            .is_err());
+    }
+}
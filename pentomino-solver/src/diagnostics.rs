@@ -0,0 +1,373 @@
+//! Reasons a board has no solution.
+//!
+//! `diagnose` looks for simple obstructions that rule out every solution
+//! without searching: a wrong number of empty cells, regions the pieces
+//! cannot fill, cells or pieces without a placement, and colourings that
+//! every placement of some piece unbalances in a way the board cannot match.
+//! Finding none does not make a board solvable. `search` then runs the search
+//! of `DefaultSolver` and counts where it got stuck: the first empty cell of
+//! a node that no remaining piece can cover.
+
+use crate::board::BoardError;
+use crate::estimator::SearchTree;
+use crate::solvers::DefaultSolver;
+use crate::{Bitboard, Piece, NUM_PIECES};
+use derive_more::Display;
+use num_traits::FromPrimitive;
+use std::collections::BTreeSet;
+
+/// Weights of `+1` and `-1` on the cells of a board.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum Colouring {
+    /// By the parity of `x + y`.
+    #[display(fmt = "checkerboard")]
+    Checkerboard,
+    /// By the parity of `y`.
+    #[display(fmt = "row")]
+    Rows,
+    /// By the parity of `x`.
+    #[display(fmt = "column")]
+    Columns,
+}
+
+impl Colouring {
+    const ALL: [Self; 3] = [Self::Checkerboard, Self::Rows, Self::Columns];
+
+    fn weight(&self, x: usize, y: usize) -> i32 {
+        let parity = match self {
+            Self::Checkerboard => x + y,
+            Self::Rows => y,
+            Self::Columns => x,
+        };
+        if parity % 2 == 0 {
+            1
+        } else {
+            -1
+        }
+    }
+}
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum Obstruction {
+    #[display(fmt = "{} empty cells, but the pieces cover 60", _0)]
+    CellCount(usize),
+    #[display(fmt = "a region of {} cells, not a multiple of 5", "_0.count_ones()")]
+    RegionSize(Bitboard),
+    #[display(fmt = "{} cells that no placement covers", "_0.count_ones()")]
+    Uncoverable(Bitboard),
+    #[display(fmt = "{} has no placement", _0)]
+    NoPlacement(Piece),
+    #[display(
+        fmt = "the {} colouring weighs {}, which no choice of placements does",
+        _0,
+        _1
+    )]
+    Colouring(Colouring, i32),
+}
+
+/// All obstructions found on a `rows` x `cols` board with the cells of
+/// `initial` filled. Regions and cells are given as bitboards.
+pub fn diagnose(rows: usize, cols: usize, initial: Bitboard) -> Vec<Obstruction> {
+    let empty = full(rows, cols) & !initial;
+    let mut ret = Vec::new();
+    if empty.count_ones() != 60 {
+        ret.push(Obstruction::CellCount(empty.count_ones() as usize));
+    }
+    ret.extend(
+        regions(cols, empty)
+            .into_iter()
+            .filter(|r| r.count_ones() % 5 != 0)
+            .map(Obstruction::RegionSize),
+    );
+
+    let mut placements: [Vec<Bitboard>; NUM_PIECES] = Default::default();
+    for (i, b) in DefaultSolver::new(rows, cols).placements() {
+        if b & initial == 0 {
+            placements[i].push(b);
+        }
+    }
+    let covered = placements.iter().flatten().fold(0, |acc, b| acc | b);
+    if empty & !covered != 0 {
+        ret.push(Obstruction::Uncoverable(empty & !covered));
+    }
+    let missing = (0..NUM_PIECES)
+        .filter(|&i| placements[i].is_empty())
+        .map(|i| Obstruction::NoPlacement(Piece::from_usize(i).unwrap()))
+        .collect::<Vec<_>>();
+
+    if ret.is_empty() && missing.is_empty() {
+        ret.extend(parity(rows, cols, empty, &placements));
+    }
+    ret.extend(missing);
+    ret
+}
+
+/// Colourings under which the `empty` cells weigh more or less than any
+/// choice of one of the `placements` of every piece. Every solution makes
+/// such a choice, so its weight must be reachable.
+fn parity(
+    rows: usize,
+    cols: usize,
+    empty: Bitboard,
+    placements: &[Vec<Bitboard>; NUM_PIECES],
+) -> Vec<Obstruction> {
+    let mut ret = Vec::new();
+    for colouring in Colouring::ALL {
+        let mut sums = BTreeSet::from([0]);
+        for v in placements {
+            let weights = v
+                .iter()
+                .map(|&b| weigh(colouring, cols, b))
+                .collect::<BTreeSet<_>>();
+            sums = sums
+                .iter()
+                .flat_map(|s| weights.iter().map(move |w| s + w))
+                .collect();
+        }
+        let total = weigh(colouring, cols, empty & full(rows, cols));
+        if !sums.contains(&total) {
+            ret.push(Obstruction::Colouring(colouring, total));
+        }
+    }
+    ret
+}
+
+/// Sum of the weights of the cells of `b` on a board `cols` wide.
+fn weigh(colouring: Colouring, cols: usize, b: Bitboard) -> i32 {
+    (0..64)
+        .filter(|z| b & (1 << z) != 0)
+        .map(|z| colouring.weight(z % cols, z / cols))
+        .sum()
+}
+
+/// Counts from a full search of a board.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchReport {
+    /// Number of solutions found.
+    pub solutions: u64,
+    /// Number of nodes visited.
+    pub nodes: u64,
+    /// Number of nodes with any children.
+    pub internal: u64,
+    /// Dead ends of the search at the cell `(x, y)`, as `dead_ends[y][x]`.
+    pub dead_ends: Vec<Vec<u64>>,
+}
+
+impl SearchReport {
+    /// The `n` cells where the search got stuck most often, as `(x, y, count)`
+    /// by decreasing count.
+    pub fn stuck_cells(&self, n: usize) -> Vec<(usize, usize, u64)> {
+        let mut cells = self
+            .dead_ends
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, &c)| (x, y, c)))
+            .filter(|&(_, _, c)| c > 0)
+            .collect::<Vec<_>>();
+        cells.sort_by_key(|&(x, y, c)| (std::cmp::Reverse(c), y, x));
+        cells.truncate(n);
+        cells
+    }
+}
+
+/// Searches all solutions of a `rows` x `cols` board with the cells of
+/// `initial` filled, counting the dead ends at every cell.
+pub fn search(rows: usize, cols: usize, initial: Bitboard) -> SearchReport {
+    search_from(rows, cols, initial, &[0; NUM_PIECES]).unwrap()
+}
+
+/// As `search`, over the solutions that extend the placed pieces of
+/// `pieces`, where a piece without cells is not placed. Fails if a placed
+/// piece is not a placement of that piece on the board, or covers a filled
+/// cell or another placed piece.
+pub fn search_from(
+    rows: usize,
+    cols: usize,
+    initial: Bitboard,
+    pieces: &[Bitboard; NUM_PIECES],
+) -> Result<SearchReport, BoardError> {
+    fn visit(
+        solver: &DefaultSolver,
+        full: Bitboard,
+        current: Bitboard,
+        used: usize,
+        report: &mut SearchReport,
+    ) {
+        report.nodes += 1;
+        if used == (1 << NUM_PIECES) - 1 {
+            report.solutions += 1;
+            return;
+        }
+        if current & full == full {
+            return;
+        }
+        let mut children = Vec::new();
+        solver.children(current, used, &mut children);
+        if children.is_empty() {
+            let cols = report.dead_ends[0].len();
+            let target = current.trailing_ones() as usize;
+            report.dead_ends[target / cols][target % cols] += 1;
+        } else {
+            report.internal += 1;
+        }
+        for (i, b) in children {
+            visit(solver, full, current | b, used | (1 << i), report);
+        }
+    }
+    let mut report = SearchReport {
+        solutions: 0,
+        nodes: 0,
+        internal: 0,
+        dead_ends: vec![vec![0; cols]; rows],
+    };
+    let solver = DefaultSolver::new(rows, cols);
+    let placements = solver.placements();
+    let full = full(rows, cols);
+    let mut current = initial | !full;
+    let mut used = 0;
+    for (i, &b) in pieces.iter().enumerate().filter(|&(_, &b)| b != 0) {
+        let piece = Piece::from_usize(i).unwrap();
+        if !placements.contains(&(i, b)) {
+            return Err(BoardError::NotAPlacement(piece));
+        }
+        if current & b != 0 {
+            return Err(BoardError::Overlap(piece));
+        }
+        current |= b;
+        used |= 1 << i;
+    }
+    visit(&solver, full, current, used, &mut report);
+    Ok(report)
+}
+
+/// The cells of a `rows` x `cols` board.
+pub(crate) fn full(rows: usize, cols: usize) -> Bitboard {
+    Bitboard::MAX >> (64 - rows * cols)
+}
+
+/// The 4-connected regions of the cells of `b` on a board `cols` wide.
+pub(crate) fn regions(cols: usize, mut b: Bitboard) -> Vec<Bitboard> {
+    let mut ret = Vec::new();
+    while b != 0 {
+        let mut region = b & b.wrapping_neg();
+        loop {
+            let next = region
+                | (region << cols)
+                | (region >> cols)
+                | ((region << 1) & !column(cols, 0))
+                | ((region >> 1) & !column(cols, cols - 1));
+            let next = next & b;
+            if next == region {
+                break;
+            }
+            region = next;
+        }
+        ret.push(region);
+        b &= !region;
+    }
+    ret
+}
+
+/// The cells of column `x` on a board `cols` wide.
+fn column(cols: usize, x: usize) -> Bitboard {
+    (x..64).step_by(cols).map(|z| 1 << z).sum()
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn obstructions() {
+        assert_eq!(diagnose(6, 10, 0), []);
+        assert_eq!(
+            diagnose(8, 8, 0),
+            [Obstruction::CellCount(64), Obstruction::RegionSize(!0)]
+        );
+
+        let holes = (1 << 0) | (1 << 2) | (1 << 9) | (1 << 16);
+        let (a, b) = (1 << 1, 1 << 8);
+        assert_eq!(
+            diagnose(8, 8, holes),
+            [
+                Obstruction::RegionSize(a),
+                Obstruction::RegionSize(!(holes | a | b)),
+                Obstruction::RegionSize(b),
+                Obstruction::Uncoverable(a | b),
+            ]
+        );
+        assert_eq!(
+            Obstruction::Uncoverable(a | b).to_string(),
+            "2 cells that no placement covers"
+        );
+
+        assert_eq!(
+            diagnose(2, 30, 0),
+            [Piece::R, Piece::T, Piece::V, Piece::W, Piece::X, Piece::Z]
+                .map(Obstruction::NoPlacement)
+        );
+    }
+
+    #[test]
+    fn colourings() {
+        let (rows, cols) = (6, 10);
+        let mut placements: [Vec<Bitboard>; NUM_PIECES] = Default::default();
+        for (i, b) in DefaultSolver::new(rows, cols).placements() {
+            placements[i].push(b);
+        }
+        assert_eq!(parity(rows, cols, full(rows, cols), &placements), []);
+
+        // with only the placements on more black cells than white ones
+        for v in placements.iter_mut() {
+            v.retain(|&b| weigh(Colouring::Checkerboard, cols, b) > 0);
+        }
+        let obstruction = Obstruction::Colouring(Colouring::Checkerboard, 0);
+        assert_eq!(
+            parity(rows, cols, full(rows, cols), &placements),
+            [obstruction]
+        );
+        assert_eq!(
+            obstruction.to_string(),
+            "the checkerboard colouring weighs 0, which no choice of placements does"
+        );
+    }
+
+    #[test]
+    fn dead_ends() {
+        let report = search(20, 3, 0);
+        assert_eq!(report.solutions, 8);
+        assert_eq!(report.stuck_cells(0), []);
+
+        // passes every check, but has no solution
+        let holes = (1 << 3) | (1 << 7) | (1 << 13);
+        assert_eq!(diagnose(21, 3, holes), []);
+        let report = search(21, 3, holes);
+        assert_eq!((report.solutions, report.nodes), (0, 1113));
+        // every other node is a dead end, as no piece is left over
+        assert_eq!(
+            report.internal,
+            report.nodes - report.dead_ends.iter().flatten().sum::<u64>()
+        );
+        assert_eq!(report.stuck_cells(2), [(1, 11, 74), (2, 9, 72)]);
+        assert_eq!(
+            report.dead_ends.iter().flatten().sum::<u64>(),
+            report
+                .stuck_cells(usize::MAX)
+                .iter()
+                .map(|c| c.2)
+                .sum::<u64>()
+        );
+
+        let mut pieces = [0; NUM_PIECES];
+        pieces[Piece::O as usize] = (0..5).map(|y| 1 << (y * 3)).sum();
+        assert!(search_from(20, 3, 0, &pieces).is_ok());
+        assert_eq!(
+            search_from(20, 3, 1, &pieces).err(),
+            Some(BoardError::Overlap(Piece::O))
+        );
+        pieces[Piece::P as usize] = pieces[Piece::O as usize] << 1;
+        assert_eq!(
+            search_from(20, 3, 0, &pieces).err(),
+            Some(BoardError::NotAPlacement(Piece::P))
+        );
+    }
+}
@@ -0,0 +1,185 @@
+//! Difficulty of puzzles: boards with some pieces placed in advance.
+//!
+//! A `Rater` measures a puzzle four ways:
+//!
+//! - `completions`: solutions that extend the placed pieces.
+//! - `nodes`: nodes of the search of `DefaultSolver`, which always fills the
+//!   first empty cell, over all completions.
+//! - `branching`: mean number of placements tried at the nodes of that search
+//!   that have any.
+//! - `deduced`: share of the pieces left to place that the rules of
+//!   `deduction::Propagator` place without searching, or 0 on a board
+//!   without 60 empty cells, where the rules don't apply.
+//!
+//! and combines them into a score from 0 to 100:
+//!
+//! ```text
+//! 40 (1 - deduced) + 40 min(1, log10(nodes) / 5)
+//!     + 20 min(1, (branching - 1) / 4) - 10 min(1, log10(completions) / 2)
+//! ```
+//!
+//! clamped to that range. Puzzles below 30 are easy and below 60 medium.
+//! Every measure only depends on the puzzle, so a rating is reproducible.
+
+use crate::deduction::Propagator;
+use crate::diagnostics;
+use crate::{Bitboard, NUM_PIECES};
+use derive_more::Display;
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Grade {
+    #[display(fmt = "easy")]
+    Easy,
+    #[display(fmt = "medium")]
+    Medium,
+    #[display(fmt = "hard")]
+    Hard,
+}
+
+/// A score with its breakdown.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rating {
+    pub completions: u64,
+    pub nodes: u64,
+    pub branching: f64,
+    pub deduced: f64,
+    pub score: f64,
+    pub grade: Grade,
+}
+
+pub struct Rater {
+    rows: usize,
+    cols: usize,
+    propagator: Propagator,
+}
+
+impl Rater {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        Self {
+            rows,
+            cols,
+            propagator: Propagator::new(rows, cols),
+        }
+    }
+    /// Rates the puzzle with the cells of `initial` filled and the pieces of
+    /// `pieces` placed, where a piece without cells is not placed. `None` if
+    /// a placed piece is not a placement on the empty cells, or the puzzle
+    /// has no solution.
+    pub fn rate(&self, initial: Bitboard, pieces: &[Bitboard; NUM_PIECES]) -> Option<Rating> {
+        let report = diagnostics::search_from(self.rows, self.cols, initial, pieces).ok()?;
+        if report.solutions == 0 {
+            return None;
+        }
+
+        let left = pieces.iter().filter(|&&b| b == 0).count();
+        // where the pieces don't exactly cover the empty cells the rules fail,
+        // and nothing counts as deduced
+        let deduced = match self.propagator.propagate(initial, pieces) {
+            Ok(deductions) if left > 0 => deductions.len() as f64 / left as f64,
+            Ok(_) => 1.0,
+            Err(_) => 0.0,
+        };
+        let branching = if report.internal > 0 {
+            (report.nodes - 1) as f64 / report.internal as f64
+        } else {
+            1.0
+        };
+        let score = 40.0 * (1.0 - deduced)
+            + 40.0 * ((report.nodes as f64).log10() / 5.0).min(1.0)
+            + 20.0 * ((branching - 1.0) / 4.0).min(1.0)
+            - 10.0 * ((report.solutions as f64).log10() / 2.0).min(1.0);
+        let score = score.clamp(0.0, 100.0);
+        let grade = if score < 30.0 {
+            Grade::Easy
+        } else if score < 60.0 {
+            Grade::Medium
+        } else {
+            Grade::Hard
+        };
+        Some(Rating {
+            completions: report.solutions,
+            nodes: report.nodes,
+            branching,
+            deduced,
+            score,
+            grade,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::solution_6x10;
+    use crate::solvers::DefaultSolver;
+    use crate::{Piece, Solver};
+
+    #[test]
+    fn ratings() {
+        let solution = solution_6x10();
+        let rater = Rater::new(6, 10);
+        let rate = |removed: u32| {
+            let mut pieces = solution;
+            for (i, b) in pieces.iter_mut().enumerate() {
+                if removed & (1 << i) != 0 {
+                    *b = 0;
+                }
+            }
+            rater.rate(0, &pieces).unwrap()
+        };
+
+        let rating = rate(0);
+        assert_eq!((rating.completions, rating.nodes), (1, 1));
+        assert_eq!(
+            (rating.branching, rating.deduced, rating.score),
+            (1.0, 1.0, 0.0)
+        );
+
+        // deduction places every piece
+        let rating = rate(0b1111);
+        assert_eq!((rating.completions, rating.nodes), (1, 5));
+        assert_eq!(rating.deduced, 1.0);
+        assert_eq!(rating.grade, Grade::Easy);
+
+        let rating = rate(0b1010_1010_1010);
+        assert_eq!((rating.completions, rating.nodes), (1, 54));
+        assert_eq!(rating.deduced, 0.5);
+        assert_eq!(rating.grade, Grade::Medium);
+
+        // deduction gets nowhere
+        let rating = rate(0b11_1111_1111);
+        assert_eq!((rating.completions, rating.nodes), (4, 212855));
+        assert_eq!(rating.deduced, 0.0);
+        assert_eq!(rating.grade, Grade::Hard);
+        assert_eq!(rate(0b11_1111_1111), rating);
+        assert_eq!(rating.grade.to_string(), "hard");
+
+        // O moved onto the bottom row leaves its own cells to X alone
+        let mut pieces = solution;
+        pieces[Piece::X as usize] = 0;
+        pieces[Piece::O as usize] = 0b11111 << 50;
+        assert_eq!(rater.rate(0, &pieces), None);
+
+        // any board, even without pieces placed
+        let rating = Rater::new(20, 3).rate(0, &[0; NUM_PIECES]).unwrap();
+        assert_eq!(rating.completions, 8);
+        assert!(rating.score > 0.0);
+        // pieces on filled cells, on each other, or not in their shape
+        let rater = Rater::new(20, 3);
+        let solution = DefaultSolver::new(20, 3).solve(0, false)[0];
+        let mut pieces = [0; NUM_PIECES];
+        pieces[Piece::X as usize] = solution[Piece::X as usize];
+        assert!(rater.rate(0, &pieces).is_some());
+        assert_eq!(rater.rate(solution[Piece::X as usize], &pieces), None);
+        pieces[Piece::O as usize] = solution[Piece::X as usize];
+        assert_eq!(rater.rate(0, &pieces), None);
+        pieces[Piece::O as usize] = 0b111_111;
+        assert_eq!(rater.rate(0, &pieces), None);
+        // a bottom row the pieces leave empty
+        let mut pieces = DefaultSolver::new(20, 3).solve(0, false)[0];
+        pieces[Piece::X as usize] = 0;
+        let rating = Rater::new(21, 3).rate(0, &pieces).unwrap();
+        assert_eq!(rating.completions, 1);
+        assert_eq!(rating.deduced, 0.0);
+    }
+}
@@ -0,0 +1,158 @@
+//! Monte Carlo estimation of the size of a solver's backtracking tree.
+//!
+//! Implements Knuth's method (*Estimating the efficiency of backtrack
+//! programs*, 1975): each probe walks from the root to a leaf choosing a
+//! uniformly random child at every level, and the product of the branching
+//! factors seen on the way gives an unbiased estimate of the tree size.
+
+use crate::rng::Rng;
+use crate::{Bitboard, NUM_PIECES};
+
+/// A backtracking tree whose nodes are `(current, used)` pairs of occupied
+/// cells and used pieces, as explored by a solver.
+pub(crate) trait SearchTree {
+    /// Appends the placements tried from the given node, as `(piece, bitboard)`.
+    fn children(&self, current: Bitboard, used: usize, children: &mut Vec<(usize, Bitboard)>);
+}
+
+/// Point estimate with a 95% confidence interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub estimate: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+impl Interval {
+    fn from_samples(samples: &[f64]) -> Self {
+        let n = samples.len() as f64;
+        if samples.is_empty() {
+            return Self {
+                estimate: 0.0,
+                lower: 0.0,
+                upper: 0.0,
+            };
+        }
+        let mean = samples.iter().sum::<f64>() / n;
+        let var = if samples.len() > 1 {
+            samples.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / (n - 1.0)
+        } else {
+            0.0
+        };
+        let half = 1.96 * (var / n).sqrt();
+        Self {
+            estimate: mean,
+            lower: (mean - half).max(0.0),
+            upper: mean + half,
+        }
+    }
+}
+
+/// Estimated cost of a full enumeration.
+///
+/// `nodes` counts every node visited by the backtracking search (including
+/// the root), `solutions` counts the leaves where all pieces are placed.
+/// Solvers that expand solutions by symmetry afterwards (such as
+/// `OptimizedSolver`) return more solutions than the leaves of their tree.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    pub probes: usize,
+    pub nodes: Interval,
+    pub solutions: Interval,
+}
+
+pub trait Estimator {
+    /// Estimates the size of the search started from `initial`, using
+    /// `probes` random root-to-leaf walks seeded with `seed`.
+    fn estimate(&self, initial: Bitboard, probes: usize, seed: u64) -> Estimate;
+}
+
+pub(crate) fn estimate<T: SearchTree + ?Sized>(
+    tree: &T,
+    initial: Bitboard,
+    probes: usize,
+    seed: u64,
+) -> Estimate {
+    let mut rng = Rng::new(seed);
+    let mut children = Vec::new();
+    let (mut nodes, mut solutions) = (Vec::with_capacity(probes), Vec::with_capacity(probes));
+    for _ in 0..probes {
+        let (mut current, mut used) = (initial, 0);
+        let (mut weight, mut count, mut found) = (1.0, 1.0, 0.0);
+        loop {
+            if used == (1 << NUM_PIECES) - 1 {
+                found = weight;
+                break;
+            }
+            children.clear();
+            tree.children(current, used, &mut children);
+            if children.is_empty() {
+                break;
+            }
+            weight *= children.len() as f64;
+            count += weight;
+            let (i, b) = children[rng.index(children.len())];
+            current |= b;
+            used |= 1 << i;
+        }
+        nodes.push(count);
+        solutions.push(found);
+    }
+    Estimate {
+        probes,
+        nodes: Interval::from_samples(&nodes),
+        solutions: Interval::from_samples(&solutions),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solvers::{DefaultSolver, OptimizedSolver, OptimizedSolverType};
+
+    fn count<T: SearchTree>(tree: &T, current: Bitboard, used: usize) -> (f64, f64) {
+        if used == (1 << NUM_PIECES) - 1 {
+            return (1.0, 1.0);
+        }
+        let mut children = Vec::new();
+        tree.children(current, used, &mut children);
+        children.iter().fold((1.0, 0.0), |(n, s), &(i, b)| {
+            let (cn, cs) = count(tree, current | b, used | (1 << i));
+            (n + cn, s + cs)
+        })
+    }
+
+    #[test]
+    fn estimate_optimized_3x20() {
+        let solver = OptimizedSolver::new(3, 20, OptimizedSolverType::SmallTable);
+        let (nodes, solutions) = count(&solver, 0, 0);
+        let e = solver.estimate(0, 20000, 1);
+        assert_eq!(e.probes, 20000);
+        assert!(e.nodes.lower <= nodes && nodes <= e.nodes.upper);
+        assert!(e.solutions.lower <= solutions && solutions <= e.solutions.upper);
+    }
+
+    #[test]
+    fn estimate_optimized_6x10() {
+        // X never lies on an axis of the 6x10 board, so each of the 2339
+        // unique solutions is one leaf of the search
+        let solver = OptimizedSolver::new(6, 10, OptimizedSolverType::SmallTable);
+        let e = solver.estimate(0, 100000, 1);
+        assert!(e.solutions.lower <= 2339.0 && 2339.0 <= e.solutions.upper);
+        assert!((e.solutions.estimate - 2339.0).abs() < 0.1 * 2339.0);
+    }
+
+    #[test]
+    fn estimate_is_reproducible() {
+        let solver = DefaultSolver::new(8, 8);
+        let initial = [27, 28, 35, 36].iter().map(|&p| 1 << p).sum::<u64>();
+        assert_eq!(
+            solver.estimate(initial, 100, 42),
+            solver.estimate(initial, 100, 42)
+        );
+        assert_ne!(
+            solver.estimate(initial, 100, 42),
+            solver.estimate(initial, 100, 43)
+        );
+    }
+}
@@ -0,0 +1,285 @@
+//! Exact-cover matrices for external solvers.
+//!
+//! A board is the exact-cover problem with a column for every piece and every
+//! empty cell, and a row (an option, in Knuth's terms) for every placement,
+//! covering its piece and its cells. `ExactCover` writes the matrix in the
+//! text format of Knuth's `dlx` programs or as a sparse CSV, and turns the
+//! rows selected by a solver back into solutions.
+//!
+//! Pieces are named by their letters, and cells by two base-36 digits for the
+//! column and the row, so `a3` is the cell `(10, 3)`.
+
+use crate::solvers::DefaultSolver;
+use crate::{Bitboard, Piece, NUM_PIECES};
+use derive_more::Display;
+use num_traits::FromPrimitive;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Display, Clone, PartialEq, Eq)]
+pub enum ExactCoverError {
+    #[display(fmt = "unknown column {:?}", _0)]
+    UnknownColumn(String),
+    #[display(fmt = "row {:?} is not a placement", _0)]
+    NotAPlacement(String),
+    #[display(fmt = "option {} out of range", _0)]
+    OptionOutOfRange(usize),
+    #[display(fmt = "malformed CSV line {:?}", _0)]
+    MalformedCsv(String),
+    #[display(fmt = "selected rows do not tile the board")]
+    NotASolution,
+}
+
+impl std::error::Error for ExactCoverError {}
+
+pub struct ExactCover {
+    rows: usize,
+    cols: usize,
+    initial: Bitboard,
+    /// Placements as `(piece, bitboard)`, one per option, by first cell and
+    /// then piece.
+    options: Vec<(usize, Bitboard)>,
+}
+
+impl ExactCover {
+    /// The matrix of the board with the cells of `initial` filled. Neither
+    /// side may be longer than 36 cells, the range of a base-36 digit.
+    pub fn new(rows: usize, cols: usize, initial: Bitboard) -> Self {
+        assert!(rows <= 36 && cols <= 36);
+        let options = DefaultSolver::new(rows, cols)
+            .placements()
+            .into_iter()
+            .filter(|&(_, b)| b & initial == 0)
+            .collect();
+        Self {
+            rows,
+            cols,
+            initial,
+            options,
+        }
+    }
+    pub fn options(&self) -> &[(usize, Bitboard)] {
+        &self.options
+    }
+    fn cell_name(&self, z: usize) -> String {
+        let digit = |n: usize| char::from_digit(n as u32, 36).unwrap();
+        format!("{}{}", digit(z % self.cols), digit(z / self.cols))
+    }
+    /// Names of the pieces, then of the empty cells in order.
+    pub fn columns(&self) -> Vec<String> {
+        (0..NUM_PIECES)
+            .map(|i| Piece::from_usize(i).unwrap().to_string())
+            .chain(
+                (0..self.rows * self.cols)
+                    .filter(|&z| self.initial & (1 << z) == 0)
+                    .map(|z| self.cell_name(z)),
+            )
+            .collect()
+    }
+    /// Names of the columns covered by an option, its piece first.
+    fn option_columns(&self, (i, b): (usize, Bitboard)) -> Vec<String> {
+        let piece = Piece::from_usize(i).unwrap().to_string();
+        let cells = (0..self.rows * self.cols).filter(move |&z| b & (1 << z) != 0);
+        std::iter::once(piece)
+            .chain(cells.map(|z| self.cell_name(z)))
+            .collect()
+    }
+    /// The matrix as input for `dlx1`: a comment, the column names, then one
+    /// line per option.
+    pub fn to_dlx(&self) -> String {
+        let mut ret = format!(
+            "| pentominoes on a {}x{} board\n{}\n",
+            self.rows,
+            self.cols,
+            self.columns().join(" ")
+        );
+        for &o in &self.options {
+            ret += &self.option_columns(o).join(" ");
+            ret += "\n";
+        }
+        ret
+    }
+    /// The matrix as `option,column` pairs, one line for every covered column
+    /// with options numbered from zero.
+    pub fn to_csv(&self) -> String {
+        let mut ret = String::from("option,column\n");
+        for (k, &o) in self.options.iter().enumerate() {
+            for name in self.option_columns(o) {
+                ret += &format!("{k},{name}\n");
+            }
+        }
+        ret
+    }
+    /// The solution made of the options numbered `selected`.
+    pub fn solution(&self, selected: &[usize]) -> Result<[Bitboard; NUM_PIECES], ExactCoverError> {
+        let mut pieces = [0; NUM_PIECES];
+        let mut covered = self.initial;
+        for &k in selected {
+            let &(i, b) = self
+                .options
+                .get(k)
+                .ok_or(ExactCoverError::OptionOutOfRange(k))?;
+            if pieces[i] != 0 || covered & b != 0 {
+                return Err(ExactCoverError::NotASolution);
+            }
+            pieces[i] = b;
+            covered |= b;
+        }
+        if covered.count_ones() as usize != self.rows * self.cols {
+            return Err(ExactCoverError::NotASolution);
+        }
+        Ok(pieces)
+    }
+    /// Reads the solutions in the output of a DLX solver that lists the
+    /// selected options by their column names, one option per line.
+    ///
+    /// A line starting with a column name is an option, in any order of its
+    /// names and ignoring anything from a `(`, such as the `(1 of 5)` that
+    /// `dlx1` appends. Any other line, such as a blank line or a solution
+    /// number, ends the current solution.
+    pub fn parse_dlx(&self, s: &str) -> Result<Vec<[Bitboard; NUM_PIECES]>, ExactCoverError> {
+        let names = self.columns().into_iter().enumerate().collect::<Vec<_>>();
+        let column = |name: &str| names.iter().find(|(_, n)| n == name).map(|&(c, _)| c);
+        let cells = (0..self.rows * self.cols)
+            .filter(|&z| self.initial & (1 << z) == 0)
+            .collect::<Vec<_>>();
+        let index = self
+            .options
+            .iter()
+            .enumerate()
+            .map(|(k, &o)| (o, k))
+            .collect::<HashMap<_, _>>();
+
+        let mut ret = Vec::new();
+        let mut selected = Vec::new();
+        for line in s.lines().chain([""]) {
+            let line = line.split('(').next().unwrap_or_default();
+            let mut tokens = line.split_whitespace().peekable();
+            if tokens.peek().and_then(|t| column(t)).is_none() {
+                if !selected.is_empty() {
+                    ret.push(self.solution(&selected)?);
+                    selected.clear();
+                }
+                continue;
+            }
+            let (mut piece, mut b) = (None, 0);
+            for t in tokens {
+                match column(t).ok_or_else(|| ExactCoverError::UnknownColumn(t.to_string()))? {
+                    c if c < NUM_PIECES && piece.is_none() => piece = Some(c),
+                    c if c >= NUM_PIECES => b |= 1 << cells[c - NUM_PIECES],
+                    _ => return Err(ExactCoverError::NotAPlacement(line.trim().to_string())),
+                }
+            }
+            let k = piece
+                .and_then(|i| index.get(&(i, b)))
+                .ok_or_else(|| ExactCoverError::NotAPlacement(line.trim().to_string()))?;
+            selected.push(*k);
+        }
+        Ok(ret)
+    }
+    /// Reads solutions from `solution,option` pairs, each selecting an option
+    /// by its number for the solution numbered `solution`. A header line is
+    /// optional; solutions are returned in order of their numbers.
+    pub fn parse_csv(&self, s: &str) -> Result<Vec<[Bitboard; NUM_PIECES]>, ExactCoverError> {
+        let mut selected = BTreeMap::<usize, Vec<usize>>::new();
+        for (n, line) in s.lines().map(str::trim).enumerate() {
+            if line.is_empty() || (n == 0 && line == "solution,option") {
+                continue;
+            }
+            let malformed = || ExactCoverError::MalformedCsv(line.to_string());
+            let (solution, option) = line.split_once(',').ok_or_else(malformed)?;
+            let solution = solution.trim().parse().map_err(|_| malformed())?;
+            let option = option.trim().parse().map_err(|_| malformed())?;
+            selected.entry(solution).or_default().push(option);
+        }
+        selected.values().map(|s| self.solution(s)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Solver;
+
+    #[test]
+    fn export() {
+        let matrix = ExactCover::new(20, 3, 0);
+        let dlx = matrix.to_dlx();
+        let mut lines = dlx.lines();
+        assert!(lines.next().unwrap().starts_with('|'));
+        let columns = lines.next().unwrap().split(' ').collect::<Vec<_>>();
+        assert_eq!(columns.len(), NUM_PIECES + 60);
+        assert_eq!(columns[..3], ["O", "P", "Q"]);
+        assert_eq!(
+            columns[NUM_PIECES..NUM_PIECES + 4],
+            ["00", "10", "20", "01"]
+        );
+        assert!(columns.contains(&"2j"));
+        assert_eq!(lines.count(), matrix.options().len());
+        // O lying across the top three rows isn't possible, standing in a
+        // column is
+        assert!(dlx.contains("\nO 00 01 02 03 04\n"));
+
+        let csv = matrix.to_csv();
+        assert_eq!(csv.lines().count(), 1 + 6 * matrix.options().len());
+        assert!(csv.starts_with("option,column\n0,"));
+
+        let holes = (1 << 0) | (1 << 1);
+        let matrix = ExactCover::new(8, 8, holes);
+        assert_eq!(matrix.columns().len(), NUM_PIECES + 62);
+        assert!(matrix.options().iter().all(|&(_, b)| b & holes == 0));
+    }
+
+    #[test]
+    fn import() {
+        let matrix = ExactCover::new(20, 3, 0);
+        let solutions = DefaultSolver::new(20, 3).solve(0, false);
+        let dlx = matrix.to_dlx();
+        let rows = dlx.lines().skip(2).collect::<Vec<_>>();
+        let number = |o: &(usize, Bitboard)| matrix.options().iter().position(|p| p == o).unwrap();
+
+        // as dlx1 prints them, with the names of each option reversed
+        let mut output = String::new();
+        let mut csv = String::from("solution,option\n");
+        for (n, s) in solutions.iter().enumerate() {
+            output += &format!("{}:\n", n + 1);
+            for (i, &b) in s.iter().enumerate() {
+                let k = number(&(i, b));
+                let names = rows[k].split(' ').rev().collect::<Vec<_>>();
+                output += &format!(" {} (1 of 3)\n", names.join(" "));
+                csv += &format!("{n},{k}\n");
+            }
+        }
+        output += "Altogether 8 solutions.\n";
+        assert_eq!(matrix.parse_dlx(&output), Ok(solutions.clone()));
+        assert_eq!(matrix.parse_csv(&csv), Ok(solutions.clone()));
+
+        let ks = solutions[0]
+            .iter()
+            .enumerate()
+            .map(|(i, &b)| number(&(i, b)))
+            .collect::<Vec<_>>();
+        assert_eq!(matrix.solution(&ks), Ok(solutions[0]));
+        assert_eq!(
+            matrix.solution(&ks[1..]),
+            Err(ExactCoverError::NotASolution)
+        );
+        assert_eq!(
+            matrix.solution(&[matrix.options().len()]),
+            Err(ExactCoverError::OptionOutOfRange(matrix.options().len()))
+        );
+        assert_eq!(
+            matrix.parse_dlx("O 00 01 02 03 0z\n"),
+            Err(ExactCoverError::UnknownColumn("0z".to_string()))
+        );
+        assert_eq!(
+            matrix.parse_dlx("O 00 01 02 03 05\n"),
+            Err(ExactCoverError::NotAPlacement(
+                "O 00 01 02 03 05".to_string()
+            ))
+        );
+        assert_eq!(
+            matrix.parse_csv("0;1\n"),
+            Err(ExactCoverError::MalformedCsv("0;1".to_string()))
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! Help for a player part way through a board.
+//!
+//! `hint` takes the pieces a player has placed, which may be wrong, and
+//! solves the board from them with a `ConstrainedSolver` by allowing every
+//! placed piece only its placement. If that has no solution, the board is
+//! solved once without the placed pieces, and those that keep it from being
+//! completed are found among its solutions and reported. Of the placements in
+//! the completions of the others, one that is in the most of them is
+//! suggested.
+//!
+//! So a hint takes one search, or two when a piece is wrong, the second as
+//! long as solving the empty board.
+
+use crate::board::Grid;
+use crate::constraints::{ConstrainedSolver, PlacementFilter};
+use crate::{Bitboard, Piece, Solver, NUM_PIECES};
+use num_traits::FromPrimitive;
+use std::collections::BTreeMap;
+
+/// A placement to make next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Suggestion {
+    pub piece: Piece,
+    pub placement: Bitboard,
+    /// Number of completions with the placement.
+    pub completions: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hint {
+    /// Placed pieces to take off before the board can be completed: those in
+    /// no solution, then, in piece order, those in no solution together with
+    /// the placed pieces kept before them.
+    pub wrong: Vec<Piece>,
+    /// Number of solutions with the placed pieces that are not wrong.
+    pub completions: usize,
+    /// A placement of a piece that is not placed or is wrong, in as many of
+    /// the completions as any. `None` if there is none or every piece is
+    /// placed.
+    pub suggestion: Option<Suggestion>,
+}
+
+/// A hint for the board of `solver` with the cells of `initial` filled and
+/// the placed pieces of `pieces`, where a piece without cells is not placed.
+/// Placements are in the coordinates of the board.
+pub fn hint<S: Solver + ConstrainedSolver + ?Sized>(
+    solver: &S,
+    initial: Bitboard,
+    pieces: &[Bitboard; NUM_PIECES],
+) -> Hint {
+    let search = |filter: &PlacementFilter| {
+        solver
+            .solve_filtered(initial, filter)
+            .iter()
+            // as the solver shows it, since some don't keep the board's layout
+            .filter_map(|s| Grid(solver.represent_solution(s)).solution().ok())
+            .map(|(_, pieces)| pieces)
+            .collect::<Vec<_>>()
+    };
+    let with = |kept: &[usize], s: &[Bitboard; NUM_PIECES]| kept.iter().all(|&i| s[i] == pieces[i]);
+
+    let placed = (0..NUM_PIECES)
+        .filter(|&i| pieces[i] != 0)
+        .collect::<Vec<_>>();
+    let filter = placed.iter().fold(PlacementFilter::new(), |f, &i| {
+        f.allow_placements(Piece::from_usize(i).unwrap(), &[pieces[i]])
+    });
+    let mut solutions = search(&filter);
+    let mut kept = placed.clone();
+    if solutions.is_empty() {
+        // the completions of any of the placed pieces are among the solutions
+        // of the board, so one search of it answers which pieces to keep
+        let all = search(&PlacementFilter::new());
+        kept.retain(|&i| all.iter().any(|s| s[i] == pieces[i]));
+        if !all.iter().any(|s| with(&kept, s)) {
+            let candidates = std::mem::take(&mut kept);
+            for i in candidates {
+                kept.push(i);
+                if !all.iter().any(|s| with(&kept, s)) {
+                    kept.pop();
+                }
+            }
+        }
+        solutions = all.into_iter().filter(|s| with(&kept, s)).collect();
+    }
+
+    let mut counts = BTreeMap::<(usize, Bitboard), usize>::new();
+    for s in &solutions {
+        for (i, &b) in s.iter().enumerate() {
+            if !kept.contains(&i) {
+                *counts.entry((i, b)).or_default() += 1;
+            }
+        }
+    }
+    // the first of the most common, so that hints are reproducible
+    let suggestion = counts
+        .into_iter()
+        .rev()
+        .max_by_key(|&(_, n)| n)
+        .map(|((i, b), n)| Suggestion {
+            piece: Piece::from_usize(i).unwrap(),
+            placement: b,
+            completions: n,
+        });
+    Hint {
+        wrong: placed
+            .into_iter()
+            .filter(|i| !kept.contains(i))
+            .map(|i| Piece::from_usize(i).unwrap())
+            .collect(),
+        completions: solutions.len(),
+        suggestion,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::transpose;
+    use crate::solvers::{DefaultSolver, OptimizedSolver, OptimizedSolverType};
+
+    #[test]
+    fn hints() {
+        let (rows, cols) = (20, 3);
+        let solver = DefaultSolver::new(rows, cols);
+        let solutions = solver.solve(0, false);
+        assert_eq!(solutions.len(), 8);
+        let with = |pieces: &[Bitboard; NUM_PIECES]| {
+            solutions
+                .iter()
+                .filter(|s| (0..NUM_PIECES).all(|i| pieces[i] == 0 || pieces[i] == s[i]))
+                .collect::<Vec<_>>()
+        };
+
+        // nothing placed: a placement in the most solutions
+        let hint = super::hint(&solver, 0, &[0; NUM_PIECES]);
+        assert!(hint.wrong.is_empty());
+        assert_eq!(hint.completions, 8);
+        let s = hint.suggestion.unwrap();
+        let count = |i: usize, b: Bitboard| solutions.iter().filter(|p| p[i] == b).count();
+        assert_eq!(s.completions, count(s.piece as usize, s.placement));
+        assert!(solutions
+            .iter()
+            .flatten()
+            .enumerate()
+            .all(|(k, &b)| count(k % NUM_PIECES, b) <= s.completions));
+
+        // placed pieces of a solution
+        let mut pieces = [0; NUM_PIECES];
+        pieces[Piece::O as usize] = solutions[0][Piece::O as usize];
+        pieces[Piece::X as usize] = solutions[0][Piece::X as usize];
+        let completions = with(&pieces);
+        let hint = super::hint(&solver, 0, &pieces);
+        assert!(hint.wrong.is_empty());
+        assert_eq!(hint.completions, completions.len());
+        let s = hint.suggestion.unwrap();
+        assert!(![Piece::O, Piece::X].contains(&s.piece));
+        assert_eq!(
+            s.completions,
+            completions
+                .iter()
+                .filter(|p| p[s.piece as usize] == s.placement)
+                .count()
+        );
+
+        // the same on the transposed board, with a solver that transposes
+        let optimized = OptimizedSolver::new(cols, rows, OptimizedSolverType::SmallTable);
+        let transposed = super::hint(&optimized, 0, &pieces.map(|b| transpose(rows, cols, b)));
+        assert_eq!(transposed.completions, hint.completions);
+        let t = transposed.suggestion.unwrap();
+        assert_eq!(t.completions, s.completions);
+
+        // X where no solution has it
+        let x = (0..)
+            .map(|k| [1, 3, 4, 5, 7].iter().map(|z| 1 << z).sum::<Bitboard>() << (3 * k))
+            .find(|&b| solutions.iter().all(|s| s[Piece::X as usize] != b))
+            .unwrap();
+        let mut wrong = pieces;
+        wrong[Piece::X as usize] = x;
+        let hint = super::hint(&solver, 0, &wrong);
+        assert_eq!(hint.wrong, [Piece::X]);
+        pieces[Piece::X as usize] = 0;
+        assert_eq!(hint.completions, with(&pieces).len());
+
+        // a piece of each of two solutions that are in none together
+        let (a, b) = solutions
+            .iter()
+            .flat_map(|a| solutions.iter().map(move |b| (a, b)))
+            .find(|(a, b)| {
+                let mut pieces = [0; NUM_PIECES];
+                pieces[Piece::P as usize] = a[Piece::P as usize];
+                pieces[Piece::Q as usize] = b[Piece::Q as usize];
+                a[Piece::P as usize] & b[Piece::Q as usize] == 0 && with(&pieces).is_empty()
+            })
+            .unwrap();
+        let mut pieces = [0; NUM_PIECES];
+        pieces[Piece::P as usize] = a[Piece::P as usize];
+        pieces[Piece::Q as usize] = b[Piece::Q as usize];
+        let hint = super::hint(&solver, 0, &pieces);
+        assert_eq!(hint.wrong, [Piece::Q]);
+        pieces[Piece::Q as usize] = 0;
+        assert_eq!(hint.completions, with(&pieces).len());
+        assert!(hint.suggestion.is_some());
+
+        // a whole solution needs no suggestion
+        let hint = super::hint(&solver, 0, &solutions[3]);
+        assert_eq!((hint.wrong.len(), hint.completions), (0, 1));
+        assert_eq!(hint.suggestion, None);
+    }
+}
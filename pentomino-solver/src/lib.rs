@@ -1,8 +1,26 @@
+pub mod board;
+pub mod codec;
+pub mod constraints;
+pub mod counter;
+pub mod db;
+pub mod deduction;
+pub mod diagnostics;
+pub mod difficulty;
+pub mod estimator;
+pub mod exact_cover;
+pub mod hints;
+pub mod milp;
 mod pieces;
+mod rng;
+pub mod sampling;
+pub mod sat;
 mod shapes;
 pub mod solvers;
+mod tables;
+pub mod zdd;
 
 pub use pieces::{Piece, NUM_PIECES};
 pub use solvers::Solver;
+pub use tables::TableError;
 
 pub type Bitboard = u64;
@@ -0,0 +1,389 @@
+//! Integer-programming models of tilings and packings.
+//!
+//! Binary variable `x<k>` places the `k`th placement of `MilpModel::placements`.
+//! Every empty cell `c_<x>_<y>` is covered at most once, or exactly once for a
+//! tiling, and every piece `p_<name>` is placed at most as often as the
+//! inventory holds, or exactly as often for a tiling. A packing maximises the
+//! number of pieces placed. The model is written in the LP and MPS formats
+//! that MILP solvers read, and a solver's solution file read back into
+//! placements.
+
+use crate::solvers::DefaultSolver;
+use crate::{Bitboard, Piece, NUM_PIECES};
+use derive_more::Display;
+use num_traits::FromPrimitive;
+
+#[derive(Debug, Display, Clone, PartialEq, Eq)]
+pub enum MilpError {
+    #[display(fmt = "invalid value {:?} for {}", _1, _0)]
+    InvalidValue(String, String),
+    #[display(fmt = "placements overlap")]
+    Overlap,
+    #[display(fmt = "piece {} placed more often than the inventory holds", _0)]
+    Inventory(Piece),
+    #[display(fmt = "placements do not tile the board")]
+    NotATiling,
+}
+
+impl std::error::Error for MilpError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Objective {
+    /// Cover every empty cell with exactly the pieces of the inventory.
+    Tiling,
+    /// Place as many pieces of the inventory as fit.
+    MaxPacking,
+}
+
+pub struct MilpModel {
+    rows: usize,
+    cols: usize,
+    initial: Bitboard,
+    objective: Objective,
+    /// Number of copies of every piece.
+    inventory: [usize; NUM_PIECES],
+    placements: Vec<(usize, Bitboard)>,
+}
+
+/// Terms per line in LP constraints, to stay within line length limits.
+const TERMS_PER_LINE: usize = 8;
+
+impl MilpModel {
+    /// The model for one copy of every piece.
+    pub fn new(rows: usize, cols: usize, initial: Bitboard, objective: Objective) -> Self {
+        Self::with_inventory(rows, cols, initial, objective, [1; NUM_PIECES])
+    }
+    /// The model for `inventory[i]` copies of piece `i`. Each placement is
+    /// used at most once, however many copies there are.
+    pub fn with_inventory(
+        rows: usize,
+        cols: usize,
+        initial: Bitboard,
+        objective: Objective,
+        inventory: [usize; NUM_PIECES],
+    ) -> Self {
+        let placements = DefaultSolver::new(rows, cols)
+            .placements()
+            .into_iter()
+            .filter(|&(i, b)| b & initial == 0 && inventory[i] > 0)
+            .collect();
+        Self {
+            rows,
+            cols,
+            initial,
+            objective,
+            inventory,
+            placements,
+        }
+    }
+    /// Placements as `(piece, bitboard)`, in variable order.
+    pub fn placements(&self) -> &[(usize, Bitboard)] {
+        &self.placements
+    }
+    /// Constraints as a name, the variables in it, its sense and right-hand
+    /// side: cells first, then pieces.
+    fn constraints(&self) -> Vec<(String, Vec<usize>, &'static str, usize)> {
+        let sense = match self.objective {
+            Objective::Tiling => "=",
+            Objective::MaxPacking => "<=",
+        };
+        let cells = (0..self.rows * self.cols)
+            .filter(|&z| self.initial & (1 << z) == 0)
+            .map(|z| {
+                let vars = (0..self.placements.len())
+                    .filter(|&k| self.placements[k].1 & (1 << z) != 0)
+                    .collect();
+                let name = format!("c_{}_{}", z % self.cols, z / self.cols);
+                (name, vars, sense, 1)
+            });
+        let pieces = (0..NUM_PIECES).filter(|&i| self.inventory[i] > 0).map(|i| {
+            let vars = (0..self.placements.len())
+                .filter(|&k| self.placements[k].0 == i)
+                .collect();
+            let name = format!("p_{}", Piece::from_usize(i).unwrap());
+            (name, vars, sense, self.inventory[i])
+        });
+        cells.chain(pieces).collect()
+    }
+    /// The objective coefficient of every variable, to be maximised.
+    fn objective_coefficient(&self) -> i32 {
+        match self.objective {
+            Objective::Tiling => 0,
+            Objective::MaxPacking => 1,
+        }
+    }
+    fn lp_sum(vars: impl IntoIterator<Item = (i32, usize)>) -> String {
+        let mut ret = String::new();
+        for (n, (c, k)) in vars.into_iter().enumerate() {
+            if n > 0 {
+                ret += if n % TERMS_PER_LINE == 0 {
+                    "\n   + "
+                } else {
+                    " + "
+                };
+            }
+            if c != 1 {
+                ret += &format!("{c} ");
+            }
+            ret += &format!("x{k}");
+        }
+        ret
+    }
+    /// The model in CPLEX LP format.
+    pub fn to_lp(&self) -> String {
+        let mut ret = format!(
+            "\\ pentominoes on a {}x{} board\nMaximize\n obj: {}\nSubject To\n",
+            self.rows,
+            self.cols,
+            Self::lp_sum((0..self.placements.len()).map(|k| (self.objective_coefficient(), k)))
+        );
+        for (name, vars, sense, rhs) in self.constraints() {
+            let sum = if vars.is_empty() {
+                // an empty cell no placement covers
+                String::from("0 x0")
+            } else {
+                Self::lp_sum(vars.into_iter().map(|k| (1, k)))
+            };
+            ret += &format!(" {name}: {sum} {sense} {rhs}\n");
+        }
+        ret += "Binary\n";
+        for k in 0..self.placements.len() {
+            ret += &format!(" x{k}\n");
+        }
+        ret += "End\n";
+        ret
+    }
+    /// The model in fixed MPS format. MPS has no objective sense, so the
+    /// objective `obj` is the number of pieces negated, to be minimised.
+    pub fn to_mps(&self) -> String {
+        let line = |f1: &str, f2: &str, f3: &str, f4: &str| {
+            format!(" {f1:<2} {f2:<8}  {f3:<8}  {f4:>12}")
+                .trim_end()
+                .to_string()
+                + "\n"
+        };
+        let constraints = self.constraints();
+        let mut ret = String::from("NAME          PENTOMINO\nROWS\n");
+        ret += &line("N", "obj", "", "");
+        for (name, _, sense, _) in &constraints {
+            ret += &line(if *sense == "=" { "E" } else { "L" }, name, "", "");
+        }
+        ret += "COLUMNS\n";
+        ret += &line("", "MARKER", "'MARKER'", "'INTORG'");
+        let mut entries = vec![Vec::new(); self.placements.len()];
+        for (name, vars, _, _) in &constraints {
+            for &k in vars {
+                entries[k].push(name.as_str());
+            }
+        }
+        for (k, rows) in entries.iter().enumerate() {
+            let var = format!("x{k}");
+            if self.objective_coefficient() != 0 {
+                let c = -self.objective_coefficient();
+                ret += &line("", &var, "obj", &c.to_string());
+            }
+            for row in rows {
+                ret += &line("", &var, row, "1");
+            }
+        }
+        ret += &line("", "MARKER", "'MARKER'", "'INTEND'");
+        ret += "RHS\n";
+        for (name, _, _, rhs) in &constraints {
+            ret += &line("", "rhs", name, &rhs.to_string());
+        }
+        ret += "BOUNDS\n";
+        for k in 0..self.placements.len() {
+            ret += &line("BV", "bnd", &format!("x{k}"), "");
+        }
+        ret += "ENDATA\n";
+        ret
+    }
+    /// Reads the placements set to 1 in a solution file.
+    ///
+    /// Any line with a variable name followed by its value is read, which
+    /// covers the `.sol` files of Gurobi and HiGHS, the solution files of CBC
+    /// and the report `glpsol -o` writes for a MIP; comment lines start with
+    /// `#` and variables not listed are 0. The placements are checked against
+    /// the model: disjoint, within the inventory and, for a tiling, covering
+    /// the board.
+    pub fn parse_solution(&self, s: &str) -> Result<Vec<(usize, Bitboard)>, MilpError> {
+        let mut selected = vec![false; self.placements.len()];
+        for line in s.lines().map(str::trim) {
+            if line.starts_with('#') {
+                continue;
+            }
+            let tokens = line.split_whitespace().collect::<Vec<_>>();
+            let Some(n) = tokens.iter().position(|t| {
+                t.strip_prefix('x')
+                    .and_then(|k| k.parse::<usize>().ok())
+                    .is_some_and(|k| k < self.placements.len())
+            }) else {
+                continue;
+            };
+            let k = tokens[n][1..].parse::<usize>().unwrap();
+            // glpsol marks integer columns with `*` between name and value
+            let v = n + 1 + (tokens.get(n + 1) == Some(&"*")) as usize;
+            let value = tokens
+                .get(v)
+                .and_then(|v| v.parse::<f64>().ok())
+                .ok_or_else(|| {
+                    let v = tokens.get(v).unwrap_or(&"").to_string();
+                    MilpError::InvalidValue(tokens[n].to_string(), v)
+                })?;
+            selected[k] = value > 0.5;
+        }
+
+        let mut covered = self.initial;
+        let mut counts = [0; NUM_PIECES];
+        let mut ret = Vec::new();
+        for (k, &(i, b)) in self.placements.iter().enumerate() {
+            if !selected[k] {
+                continue;
+            }
+            if covered & b != 0 {
+                return Err(MilpError::Overlap);
+            }
+            counts[i] += 1;
+            if counts[i] > self.inventory[i] {
+                return Err(MilpError::Inventory(Piece::from_usize(i).unwrap()));
+            }
+            covered |= b;
+            ret.push((i, b));
+        }
+        if self.objective == Objective::Tiling
+            && (counts != self.inventory || covered.count_ones() as usize != self.rows * self.cols)
+        {
+            return Err(MilpError::NotATiling);
+        }
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Solver;
+
+    #[test]
+    fn export() {
+        let model = MilpModel::new(20, 3, 0, Objective::Tiling);
+        let n = model.placements().len();
+        let lp = model.to_lp();
+        assert!(lp.contains("\nSubject To\n c_0_0: x0 + "));
+        assert!(lp.contains("\n p_Z: "));
+        assert_eq!(lp.matches(" = 1\n").count(), 60 + NUM_PIECES);
+        assert!(lp.ends_with(&format!(" x{}\nEnd\n", n - 1)));
+
+        let mps = model.to_mps();
+        let section = |name: &str| {
+            mps.split(&format!("\n{name}\n"))
+                .nth(1)
+                .unwrap()
+                .lines()
+                .take_while(|l| l.starts_with(' '))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(section("ROWS").len(), 1 + 60 + NUM_PIECES);
+        assert_eq!(section("RHS").len(), 60 + NUM_PIECES);
+        assert_eq!(section("BOUNDS").len(), n);
+        // every placement covers its piece and 5 cells
+        assert_eq!(section("COLUMNS").len(), 2 + 6 * n);
+        assert!(mps.contains("\n    x0        c_0_0                1\n"));
+
+        let inventory = [2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let model = MilpModel::with_inventory(6, 10, 0, Objective::MaxPacking, inventory);
+        assert!(model.placements().iter().all(|&(i, _)| i == 0));
+        let lp = model.to_lp();
+        assert!(lp.contains(" p_O: ") && lp.contains(" <= 2\n"));
+        assert!(!lp.contains(" p_P: "));
+        assert!(model
+            .to_mps()
+            .contains("\n    x0        obj                 -1\n"));
+    }
+
+    #[test]
+    fn parse_solution() {
+        let model = MilpModel::new(20, 3, 0, Objective::Tiling);
+        let solution = DefaultSolver::new(20, 3).solve(0, false)[0];
+        // in variable order
+        let placements = model
+            .placements()
+            .iter()
+            .copied()
+            .filter(|&(i, b)| solution[i] == b)
+            .collect::<Vec<_>>();
+        let value = |k: usize| placements.contains(&model.placements()[k]) as u8;
+
+        // Gurobi
+        let mut sol = String::from("# Objective value = 0\n");
+        for k in 0..model.placements().len() {
+            sol += &format!("x{k} {}\n", value(k));
+        }
+        assert_eq!(model.parse_solution(&sol), Ok(placements.clone()));
+        // CBC, listing only the nonzero variables
+        let mut sol = String::from("Optimal - objective value 0.00000000\n");
+        for k in (0..model.placements().len()).filter(|&k| value(k) == 1) {
+            sol += &format!("{k:>7} x{k:<16} 1 0\n");
+        }
+        assert_eq!(model.parse_solution(&sol), Ok(placements.clone()));
+        // glpsol -o, whose rows must not be mistaken for variables
+        let mut glpk = String::from(
+            "Problem:    \n\
+             Rows:       72\n\
+             Columns:    1236 (1236 integer, 1236 binary)\n\
+             Non-zeros:  7416\n\
+             Status:     INTEGER OPTIMAL\n\
+             Objective:  obj = 0 (MINimum)\n\
+             \n   No.   Row name        Activity     Lower bound   Upper bound\n\
+             ------ ------------    ------------- ------------- -------------\n\
+             \x20    1 c_0_0                       1             1             = \n\
+             \n   No. Column name       Activity     Lower bound   Upper bound\n\
+             ------ ------------    ------------- ------------- -------------\n",
+        );
+        for k in 0..model.placements().len() {
+            glpk += &format!(
+                "{:>6} x{k:<11} * {:>13} {:>13} {:>13}\n",
+                k + 1,
+                value(k),
+                0,
+                1
+            );
+        }
+        glpk += "\nInteger feasibility conditions:\n\n\
+                KKT.PE: max.abs.err = 0.00e+00 on row 0\n\
+                \x20       max.rel.err = 0.00e+00 on row 0\n\
+                \x20       High quality\n\n\
+                End of output\n";
+        assert_eq!(model.placements().len(), 1236);
+        assert!(
+            glpk.contains("\n     1 x0           *             0             0             1\n")
+        );
+        assert_eq!(model.parse_solution(&glpk), Ok(placements.clone()));
+
+        let partial = sol.lines().take(12).collect::<Vec<_>>().join("\n");
+        assert_eq!(model.parse_solution(&partial), Err(MilpError::NotATiling));
+        let packing = MilpModel::new(20, 3, 0, Objective::MaxPacking);
+        assert_eq!(packing.parse_solution(&partial).unwrap().len(), 11);
+        assert_eq!(
+            model.parse_solution("x0 one\n"),
+            Err(MilpError::InvalidValue("x0".to_string(), "one".to_string()))
+        );
+        let overlap = model
+            .placements()
+            .iter()
+            .enumerate()
+            .skip(1)
+            .find(|(_, &(_, b))| b & model.placements()[0].1 != 0)
+            .unwrap()
+            .0;
+        assert_eq!(
+            packing.parse_solution(&format!("x0 1\nx{overlap} 1\n")),
+            Err(MilpError::Overlap)
+        );
+    }
+}
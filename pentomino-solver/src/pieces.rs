@@ -1,9 +1,11 @@
+use crate::shapes::calculate_shapes;
 use num_derive::FromPrimitive;
 use std::fmt::Write;
 
 pub const NUM_PIECES: usize = 12;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Piece {
     O = 0,
     P,
@@ -19,6 +21,15 @@ pub enum Piece {
     Z,
 }
 
+impl Piece {
+    /// The distinct orientations of the piece as cells `(x, y)` from the top
+    /// left corner of its bounding box. A placement's orientation is its
+    /// index in this list.
+    pub fn orientations(&self) -> Vec<Vec<(usize, usize)>> {
+        calculate_shapes().swap_remove(*self as usize)
+    }
+}
+
 impl std::fmt::Display for Piece {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_char(match self {
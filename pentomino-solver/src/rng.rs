@@ -0,0 +1,52 @@
+/// Small deterministic PRNG (xorshift64* seeded through splitmix64).
+///
+/// The output sequence for a given seed is part of the crate's behaviour, so
+/// seeded results stay reproducible across versions and targets.
+#[derive(Debug, Clone)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        Self(if z == 0 { 0x9E37_79B9_7F4A_7C15 } else { z })
+    }
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+    /// Uniform integer in `0..n`, the same on every target.
+    pub(crate) fn below(&mut self, n: u64) -> u64 {
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+    /// Uniform index into a slice of length `n`.
+    pub(crate) fn index(&mut self, n: usize) -> usize {
+        self.below(n as u64) as usize
+    }
+    /// Shuffles `v` uniformly (Fisher-Yates).
+    pub(crate) fn shuffle<T>(&mut self, v: &mut [T]) {
+        for i in (1..v.len()).rev() {
+            v.swap(i, self.index(i + 1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn below() {
+        let mut rng = Rng::new(0);
+        let n = 1 << 40;
+        let v = (0..64).map(|_| rng.below(n)).collect::<Vec<_>>();
+        assert!(v.iter().all(|&r| r < n));
+        // past the range of a 32-bit usize
+        assert!(v.iter().any(|&r| r > u32::MAX as u64));
+        assert_eq!(Rng::new(0).index(10), Rng::new(0).below(10) as usize);
+    }
+}
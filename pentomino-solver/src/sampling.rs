@@ -0,0 +1,177 @@
+//! Random solutions: a seeded randomised search, and an exact uniform sampler.
+//!
+//! `RandomSearch` returns the first solution of a backtracking search that
+//! tries the children of every node in a shuffled order. It is cheap and
+//! reproducible, but not uniform: solutions in small subtrees are more likely.
+//! `UniformSampler` compiles the board into a ZDD once and then draws every
+//! solution with equal probability without enumerating them.
+
+use crate::estimator::SearchTree;
+use crate::rng::Rng;
+use crate::solvers::DefaultSolver;
+use crate::zdd::Zdd;
+use crate::{Bitboard, NUM_PIECES};
+
+pub trait RandomSearch {
+    /// The first solution found from `initial` when the placements tried at
+    /// every step are shuffled with `seed`, or `None` if there is none.
+    fn random_solution(&self, initial: Bitboard, seed: u64) -> Option<[Bitboard; NUM_PIECES]>;
+}
+
+pub(crate) fn random_solution<T: SearchTree + ?Sized>(
+    tree: &T,
+    initial: Bitboard,
+    rng: &mut Rng,
+) -> Option<[Bitboard; NUM_PIECES]> {
+    fn search<T: SearchTree + ?Sized>(
+        tree: &T,
+        current: Bitboard,
+        used: usize,
+        rng: &mut Rng,
+        pieces: &mut [Bitboard; NUM_PIECES],
+    ) -> bool {
+        if used == (1 << NUM_PIECES) - 1 {
+            return true;
+        }
+        let mut children = Vec::new();
+        tree.children(current, used, &mut children);
+        rng.shuffle(&mut children);
+        children.into_iter().any(|(i, b)| {
+            pieces[i] = b;
+            search(tree, current | b, used | (1 << i), rng, pieces)
+        })
+    }
+    let mut pieces = [Bitboard::default(); NUM_PIECES];
+    search(tree, initial, 0, rng, &mut pieces).then_some(pieces)
+}
+
+/// Draws solutions of a board uniformly at random.
+pub struct UniformSampler {
+    rows: usize,
+    cols: usize,
+    /// Whether the diagram is built on the transposed board, so that its
+    /// rows are the short side.
+    transposed: bool,
+    zdd: Zdd,
+    /// Symmetries of the (possibly transposed) board that fix the initial
+    /// cells, starting with the identity.
+    symmetries: Vec<Vec<usize>>,
+}
+
+/// Maps a bitboard on a `rows` x `cols` board to the transposed board.
+pub(crate) fn transpose(rows: usize, cols: usize, b: Bitboard) -> Bitboard {
+    (0..rows * cols)
+        .filter(|&z| b & (1 << z) != 0)
+        .map(|z| 1 << (z / cols + z % cols * rows))
+        .sum()
+}
+
+impl UniformSampler {
+    pub fn new(rows: usize, cols: usize, initial: Bitboard) -> Self {
+        let transposed = cols > rows;
+        let (solver, initial) = if transposed {
+            (
+                DefaultSolver::new(cols, rows),
+                transpose(rows, cols, initial),
+            )
+        } else {
+            (DefaultSolver::new(rows, cols), initial)
+        };
+        Self {
+            rows,
+            cols,
+            transposed,
+            zdd: solver.zdd(initial),
+            symmetries: solver
+                .symmetries()
+                .into_iter()
+                .filter(|g| DefaultSolver::apply(g, initial) == initial)
+                .collect(),
+        }
+    }
+    /// Number of solutions, or of solutions up to the symmetries of the board
+    /// that fix the initial cells.
+    pub fn count(&self, unique: bool) -> u64 {
+        if unique {
+            // no solution is symmetric to itself, so all classes are the same size
+            self.zdd.count() / self.symmetries.len() as u64
+        } else {
+            self.zdd.count()
+        }
+    }
+    /// A solution drawn uniformly with `seed`, or `None` if there is none.
+    ///
+    /// With `unique`, the class of the solution is uniform among all classes,
+    /// and its smallest member is returned.
+    pub fn sample(&self, seed: u64, unique: bool) -> Option<[Bitboard; NUM_PIECES]> {
+        let mut pieces = self.zdd.sample(seed)?;
+        if unique {
+            pieces = self
+                .symmetries
+                .iter()
+                .map(|g| pieces.map(|b| DefaultSolver::apply(g, b)))
+                .min()
+                .unwrap_or(pieces);
+        }
+        if self.transposed {
+            pieces = pieces.map(|b| transpose(self.cols, self.rows, b));
+        }
+        Some(pieces)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solvers::{OptimizedSolver, OptimizedSolverType};
+    use crate::Solver;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn random_search_is_reproducible() {
+        let solver = OptimizedSolver::new(3, 20, OptimizedSolverType::SmallTable);
+        let all = solver.solve(0, false);
+        let found = (0..20)
+            .map(|seed| solver.random_solution(0, seed).unwrap())
+            .collect::<Vec<_>>();
+        assert!(found.iter().all(|s| all.contains(s)));
+        assert!(found.iter().any(|s| s != &found[0]));
+        assert_eq!(solver.random_solution(0, 7), Some(found[7]));
+
+        let solver = DefaultSolver::new(20, 3);
+        let all = solver.solve(0, false);
+        assert!(all.contains(&solver.random_solution(0, 3).unwrap()));
+        assert_eq!(solver.random_solution(1, 0), None);
+    }
+
+    #[test]
+    fn uniform_sampler() {
+        let sampler = UniformSampler::new(3, 20, 0);
+        // the optimized solver returns solutions on its own transposed board
+        let solver = OptimizedSolver::new(3, 20, OptimizedSolverType::SmallTable);
+        let all = solver
+            .solve(0, false)
+            .iter()
+            .map(|s| solver.represent_solution(s))
+            .collect::<Vec<_>>();
+        let default = DefaultSolver::new(3, 20);
+        assert_eq!(sampler.count(false), 8);
+        assert_eq!(sampler.count(true), 2);
+
+        let mut hits = BTreeMap::new();
+        for seed in 0..800 {
+            let sample = sampler.sample(seed, false).unwrap();
+            assert!(all.contains(&default.represent_solution(&sample)));
+            *hits.entry(sample).or_insert(0) += 1;
+        }
+        assert_eq!(hits.len(), 8);
+        assert!(hits.values().all(|&n| (50..150).contains(&n)), "{hits:?}");
+
+        let mut hits = BTreeMap::new();
+        for seed in 0..400 {
+            *hits.entry(sampler.sample(seed, true).unwrap()).or_insert(0) += 1;
+        }
+        assert_eq!(hits.len(), 2);
+        assert!(hits.values().all(|&n| (150..250).contains(&n)), "{hits:?}");
+    }
+}
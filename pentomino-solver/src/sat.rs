@@ -0,0 +1,285 @@
+//! Boolean satisfiability: CNF formulas and DIMACS input and output.
+//!
+//! Formulas are solved with varisat when the `sat` feature is enabled.
+//! Without it, or for harder instances, they can be written out with
+//! `Cnf::to_dimacs`, solved by an external solver, and its output read back
+//! with `parse_model`.
+
+use derive_more::Display;
+
+/// A literal in DIMACS form: `v` or `-v` for a variable `v >= 1`.
+pub type Lit = i32;
+
+#[derive(Debug, Display, Clone, PartialEq, Eq)]
+pub enum DimacsError {
+    #[display(fmt = "missing or malformed problem line")]
+    MissingHeader,
+    #[display(fmt = "invalid literal {:?}", _0)]
+    InvalidLiteral(String),
+    #[display(fmt = "variable {} out of range", _0)]
+    VariableOutOfRange(i64),
+    #[display(fmt = "formula is unsatisfiable")]
+    Unsatisfiable,
+    /// The solver gave up, e.g. on a timeout, or printed no answer at all.
+    #[display(fmt = "solver gave no answer (status {:?})", _0)]
+    Unknown(String),
+}
+
+impl std::error::Error for DimacsError {}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cnf {
+    num_vars: usize,
+    clauses: Vec<Vec<Lit>>,
+}
+
+impl Cnf {
+    pub fn new(num_vars: usize) -> Self {
+        Self {
+            num_vars,
+            clauses: Vec::new(),
+        }
+    }
+    pub fn num_vars(&self) -> usize {
+        self.num_vars
+    }
+    pub fn clauses(&self) -> &[Vec<Lit>] {
+        &self.clauses
+    }
+    pub fn new_var(&mut self) -> Lit {
+        self.num_vars += 1;
+        self.num_vars as Lit
+    }
+    pub fn add_clause(&mut self, clause: impl IntoIterator<Item = Lit>) {
+        let clause = clause.into_iter().collect::<Vec<_>>();
+        debug_assert!(clause
+            .iter()
+            .all(|&l| l != 0 && l.unsigned_abs() as usize <= self.num_vars));
+        self.clauses.push(clause);
+    }
+    /// At most one of `lits` is true, as one binary clause per pair.
+    pub fn at_most_one(&mut self, lits: &[Lit]) {
+        for (i, &a) in lits.iter().enumerate() {
+            for &b in &lits[i + 1..] {
+                self.add_clause([-a, -b]);
+            }
+        }
+    }
+    pub fn exactly_one(&mut self, lits: &[Lit]) {
+        self.add_clause(lits.iter().copied());
+        self.at_most_one(lits);
+    }
+    pub fn to_dimacs(&self) -> String {
+        let mut ret = format!("p cnf {} {}\n", self.num_vars, self.clauses.len());
+        for clause in &self.clauses {
+            for l in clause {
+                ret += &format!("{l} ");
+            }
+            ret += "0\n";
+        }
+        ret
+    }
+    pub fn from_dimacs(s: &str) -> Result<Self, DimacsError> {
+        let mut cnf = None;
+        let mut clause = Vec::new();
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('c') {
+                continue;
+            }
+            // end marker of the SATLIB benchmark files
+            if line.starts_with('%') {
+                break;
+            }
+            if let Some(header) = line.strip_prefix("p ") {
+                let fields = header.split_whitespace().collect::<Vec<_>>();
+                match fields[..] {
+                    ["cnf", vars, _] if cnf.is_none() => {
+                        let vars = vars.parse().map_err(|_| DimacsError::MissingHeader)?;
+                        cnf = Some(Self::new(vars));
+                    }
+                    _ => return Err(DimacsError::MissingHeader),
+                }
+                continue;
+            }
+            let cnf = cnf.as_mut().ok_or(DimacsError::MissingHeader)?;
+            for token in line.split_whitespace() {
+                match parse_lit(token, cnf.num_vars)? {
+                    0 => cnf.clauses.push(std::mem::take(&mut clause)),
+                    l => clause.push(l),
+                }
+            }
+        }
+        let mut cnf = cnf.ok_or(DimacsError::MissingHeader)?;
+        if !clause.is_empty() {
+            cnf.clauses.push(clause);
+        }
+        Ok(cnf)
+    }
+    /// A satisfying assignment, indexed by variable minus one, or `None` if
+    /// there is none.
+    #[cfg(feature = "sat")]
+    pub fn solve(&self) -> Option<Vec<bool>> {
+        Incremental::new(self).solve()
+    }
+}
+
+fn parse_lit(token: &str, num_vars: usize) -> Result<Lit, DimacsError> {
+    let l = token
+        .parse::<i64>()
+        .map_err(|_| DimacsError::InvalidLiteral(token.to_string()))?;
+    if l.unsigned_abs() as usize > num_vars {
+        return Err(DimacsError::VariableOutOfRange(l));
+    }
+    Ok(l as Lit)
+}
+
+/// Reads an assignment of `num_vars` variables from solver output, either in
+/// the SAT competition format (`s` and `v` lines) or as a bare list of
+/// literals. Variables not mentioned are false.
+///
+/// `s UNSATISFIABLE` is `DimacsError::Unsatisfiable`. Any other status, such
+/// as `s UNKNOWN`, or output with neither a status nor literals is
+/// `DimacsError::Unknown`, since nothing is known about the formula then.
+pub fn parse_model(s: &str, num_vars: usize) -> Result<Vec<bool>, DimacsError> {
+    let mut model = vec![false; num_vars];
+    let mut answered = false;
+    for line in s.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('c') {
+            continue;
+        }
+        answered = true;
+        if let Some(status) = line.strip_prefix('s') {
+            match status.trim() {
+                "SATISFIABLE" => continue,
+                "UNSATISFIABLE" => return Err(DimacsError::Unsatisfiable),
+                status => return Err(DimacsError::Unknown(status.to_string())),
+            }
+        }
+        for token in line.strip_prefix('v').unwrap_or(line).split_whitespace() {
+            let l = parse_lit(token, num_vars)?;
+            if l > 0 {
+                model[l as usize - 1] = true;
+            }
+        }
+    }
+    if !answered {
+        return Err(DimacsError::Unknown(String::new()));
+    }
+    Ok(model)
+}
+
+/// Incremental solver: clauses can be added between calls to `solve`, which
+/// is how solutions are enumerated with blocking clauses.
+#[cfg(feature = "sat")]
+pub(crate) struct Incremental {
+    solver: varisat::Solver<'static>,
+    num_vars: usize,
+}
+
+#[cfg(feature = "sat")]
+impl Incremental {
+    pub(crate) fn new(cnf: &Cnf) -> Self {
+        let mut ret = Self {
+            solver: varisat::Solver::new(),
+            num_vars: cnf.num_vars,
+        };
+        for clause in &cnf.clauses {
+            ret.add_clause(clause);
+        }
+        ret
+    }
+    pub(crate) fn add_clause(&mut self, clause: &[Lit]) {
+        use varisat::ExtendFormula;
+        let clause = clause
+            .iter()
+            .map(|&l| varisat::Lit::from_dimacs(l as isize))
+            .collect::<Vec<_>>();
+        self.solver.add_clause(&clause);
+    }
+    pub(crate) fn solve(&mut self) -> Option<Vec<bool>> {
+        if !self.solver.solve().ok()? {
+            return None;
+        }
+        // variables in no clause are left out of the model, and are false
+        let mut model = vec![false; self.num_vars];
+        for l in self.solver.model()? {
+            model[l.index()] = l.is_positive();
+        }
+        Some(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "sat")]
+    #[test]
+    fn pigeonhole() {
+        // n + 1 pigeons in n holes; variable 1 + p * n + h puts pigeon p in hole h
+        for n in 1..6 {
+            let var = |p: usize, h: usize| (1 + p * n + h) as Lit;
+            let mut cnf = Cnf::new((n + 1) * n);
+            for p in 0..n {
+                cnf.exactly_one(&(0..n).map(|h| var(p, h)).collect::<Vec<_>>());
+            }
+            for h in 0..n {
+                cnf.at_most_one(&(0..=n).map(|p| var(p, h)).collect::<Vec<_>>());
+            }
+            let model = cnf.solve().unwrap();
+            for h in 0..n {
+                assert!((0..n).filter(|&p| model[var(p, h) as usize - 1]).count() <= 1);
+            }
+            cnf.add_clause((0..n).map(|h| var(n, h)));
+            assert_eq!(cnf.solve(), None);
+        }
+    }
+
+    #[test]
+    fn dimacs() {
+        let s = "c example\np cnf 3 2\n1 -3 0\n2 3\n-1 0\n";
+        let cnf = Cnf::from_dimacs(s).unwrap();
+        assert_eq!(cnf.clauses(), [vec![1, -3], vec![2, 3, -1]]);
+        assert_eq!(Cnf::from_dimacs(&cnf.to_dimacs()), Ok(cnf));
+        assert_eq!(Cnf::from_dimacs("1 2 0\n"), Err(DimacsError::MissingHeader));
+        assert_eq!(
+            Cnf::from_dimacs("p cnf 2 1\n1 3 0\n"),
+            Err(DimacsError::VariableOutOfRange(3))
+        );
+
+        assert_eq!(
+            parse_model("s SATISFIABLE\nv 1 -2\nv 3 0\n", 3),
+            Ok(vec![true, false, true])
+        );
+        assert_eq!(parse_model("-1 2 0", 2), Ok(vec![false, true]));
+        assert_eq!(
+            parse_model("s UNSATISFIABLE\n", 2),
+            Err(DimacsError::Unsatisfiable)
+        );
+        assert_eq!(
+            parse_model(
+                "c timed out
+s UNKNOWN
+",
+                2
+            ),
+            Err(DimacsError::Unknown("UNKNOWN".to_string()))
+        );
+        assert_eq!(
+            parse_model(
+                "s INDETERMINATE
+",
+                2
+            ),
+            Err(DimacsError::Unknown("INDETERMINATE".to_string()))
+        );
+        assert_eq!(
+            parse_model(
+                "c interrupted
+",
+                2
+            ),
+            Err(DimacsError::Unknown(String::new()))
+        );
+    }
+}
@@ -153,6 +153,31 @@ const BLOCKS: [Matrix; NUM_PIECES] = [
     ]),
 ];
 
+/// Index of the orientation in `shapes[i]` that covers `cells` when moved to
+/// the corner, or `None` if the cells are not a placement of piece `i`.
+pub(crate) fn orientation(
+    shapes: &[Vec<Vec<(usize, usize)>>],
+    i: usize,
+    cells: &[(usize, usize)],
+) -> Option<usize> {
+    let (x0, y0) = cells
+        .iter()
+        .fold((usize::MAX, usize::MAX), |(xmin, ymin), &(x, y)| {
+            (xmin.min(x), ymin.min(y))
+        });
+    let mut cells = cells
+        .iter()
+        .map(|&(x, y)| (x - x0, y - y0))
+        .collect::<Vec<_>>();
+    cells.sort_unstable();
+    cells.dedup();
+    shapes[i].iter().position(|s| {
+        let mut s = s.clone();
+        s.sort_unstable();
+        s == cells
+    })
+}
+
 pub(crate) fn calculate_shapes() -> Vec<Vec<Vec<(usize, usize)>>> {
     let mut ret = Vec::new();
     for block in BLOCKS {
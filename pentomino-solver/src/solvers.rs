@@ -1,10 +1,15 @@
 mod default;
+mod local;
 mod optimized;
+mod sat;
 mod simple;
 
 use crate::{Bitboard, Piece, NUM_PIECES};
 pub use default::DefaultSolver;
-pub use optimized::{OptimizedSolver, OptimizedSolverType};
+pub use local::{LocalSearchSolver, Packing, RegionPacking, RegionSearch};
+use num_traits::FromPrimitive;
+pub use optimized::{CustomOrder, OptimizedSolver, OptimizedSolverType, ScanOrder, ScanOrderError};
+pub use sat::SatSolver;
 pub use simple::SimpleSolver;
 
 pub trait Solver {
@@ -12,7 +17,27 @@ pub trait Solver {
     fn represent_solution(&self, solution: &[Bitboard; NUM_PIECES]) -> Vec<Vec<Option<Piece>>>;
 }
 
+/// The pieces of `solution` on a board of `rows` by `cols` cells, row by row,
+/// as the solvers' `represent_solution` shows it.
+pub(crate) fn represent(
+    rows: usize,
+    cols: usize,
+    solution: &[Bitboard; NUM_PIECES],
+) -> Vec<Vec<Option<Piece>>> {
+    let mut ret = vec![vec![None; cols]; rows];
+    for (i, b) in solution.iter().enumerate() {
+        let p = Piece::from_usize(i);
+        for (y, row) in ret.iter_mut().enumerate() {
+            for (x, col) in row.iter_mut().enumerate() {
+                if b & (1 << (x + y * cols)) != 0 {
+                    *col = p;
+                }
+            }
+        }
+    }
+    ret
+}
+
 pub(crate) trait SolutionStore {
     fn add_solution(&mut self, pieces: &[Bitboard; NUM_PIECES]);
-    fn get_solutions(&self) -> Vec<[Bitboard; NUM_PIECES]>;
 }
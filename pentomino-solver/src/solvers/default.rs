@@ -1,48 +1,15 @@
-use super::{SolutionStore, Solver};
+use super::{represent, SolutionStore, Solver};
+use crate::constraints::{
+    ConstrainedSolver, ConstrainedStore, Constraint, Constraints, PlacementFilter,
+};
+use crate::estimator::{self, Estimate, Estimator, SearchTree};
+use crate::rng::Rng;
+use crate::sampling::{self, RandomSearch};
 use crate::shapes::calculate_shapes;
+use crate::tables::{Kind, Reader, TableError, Writer};
+use crate::zdd::Zdd;
 use crate::{Bitboard, Piece, NUM_PIECES};
-use num_traits::FromPrimitive;
 use std::array;
-use std::collections::HashSet;
-
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct Board(Vec<Vec<Option<Piece>>>);
-
-impl Board {
-    fn is_square(&self) -> bool {
-        let (rows, cols) = (self.0.len(), self.0[0].len());
-        rows == cols
-    }
-    fn flip_x(&self) -> Self {
-        let mut ret = self.clone();
-        for row in ret.0.iter_mut() {
-            row.reverse();
-        }
-        ret
-    }
-    fn flip_y(&self) -> Self {
-        let mut ret = self.clone();
-        ret.0.reverse();
-        ret
-    }
-    fn flip_xy(&self) -> Self {
-        let mut ret = self.clone();
-        for row in ret.0.iter_mut() {
-            row.reverse();
-        }
-        ret.0.reverse();
-        ret
-    }
-    fn transpose(&self) -> Self {
-        let mut ret = self.clone();
-        for (y, row) in ret.0.iter_mut().enumerate() {
-            for (x, col) in row.iter_mut().enumerate() {
-                *col = self.0[x][y];
-            }
-        }
-        ret
-    }
-}
 
 #[derive(Default)]
 struct AllSolutionStore {
@@ -53,44 +20,25 @@ impl SolutionStore for AllSolutionStore {
     fn add_solution(&mut self, pieces: &[Bitboard; NUM_PIECES]) {
         self.solutions.push(*pieces);
     }
-    fn get_solutions(&self) -> Vec<[Bitboard; NUM_PIECES]> {
-        self.solutions.clone()
-    }
 }
 
-type Converter<'a> = Box<dyn Fn(&[Bitboard; NUM_PIECES]) -> Board + 'a>;
-
-struct UniqueSolutionStore<'a> {
-    converter: Converter<'a>,
+/// Keeps a solution only if it is the smallest of its images under
+/// `symmetries`, the board symmetries fixing the X placement being searched.
+#[derive(Default)]
+struct CanonicalSolutionStore {
+    symmetries: Vec<Vec<usize>>,
     solutions: Vec<[Bitboard; NUM_PIECES]>,
-    set: HashSet<Board>,
-}
-
-impl<'a> UniqueSolutionStore<'a> {
-    fn new(converter: impl Fn(&[Bitboard; NUM_PIECES]) -> Board + 'a) -> Self {
-        Self {
-            converter: Box::new(converter),
-            solutions: Vec::new(),
-            set: HashSet::new(),
-        }
-    }
 }
 
-impl<'a> SolutionStore for UniqueSolutionStore<'a> {
+impl SolutionStore for CanonicalSolutionStore {
     fn add_solution(&mut self, pieces: &[Bitboard; NUM_PIECES]) {
-        let board = (self.converter)(pieces);
-        if !self.set.contains(&board) {
+        if self
+            .symmetries
+            .iter()
+            .all(|g| &pieces.map(|b| DefaultSolver::apply(g, b)) >= pieces)
+        {
             self.solutions.push(*pieces);
         }
-        self.set.insert(board.flip_x());
-        self.set.insert(board.flip_y());
-        self.set.insert(board.flip_xy());
-        if board.is_square() {
-            self.set.insert(board.transpose());
-        }
-    }
-    fn get_solutions(&self) -> Vec<[Bitboard; NUM_PIECES]> {
-        self.solutions.clone()
     }
 }
 
@@ -104,38 +52,138 @@ impl DefaultSolver {
     pub fn new(rows: usize, cols: usize) -> Self {
         assert!(rows * cols <= 64);
         let shapes = calculate_shapes();
-        let mut table = array::from_fn(|_| array::from_fn(|_| Vec::new()));
+        let mut placements = Vec::new();
         for (n, shape) in shapes.iter().enumerate() {
             for s in shape {
                 if s.iter().any(|&(x, y)| x >= cols || y >= rows) {
                     continue;
                 }
-                let v = s.iter().map(|p| (1 << (p.0 + p.1 * cols))).sum::<u64>();
+                let v = s.iter().map(|p| 1 << (p.0 + p.1 * cols)).sum::<u64>();
                 let (w, h) = s
                     .iter()
                     .fold((0, 0), |(xmax, ymax), &(x, y)| (xmax.max(x), ymax.max(y)));
                 for y in 0..rows - h {
                     for x in 0..cols - w {
-                        let offset = x + y * cols;
-                        table[s[0].0 + offset][n].push(v << offset);
+                        placements.push((n, v << (x + y * cols)));
                     }
                 }
             }
         }
+        Self::from_placements(rows, cols, &placements)
+    }
+    fn from_placements(rows: usize, cols: usize, placements: &[(usize, Bitboard)]) -> Self {
+        let mut table: [[Vec<Bitboard>; NUM_PIECES]; 64] =
+            array::from_fn(|_| array::from_fn(|_| Vec::new()));
+        for &(n, u) in placements {
+            table[u.trailing_zeros() as usize][n].push(u);
+        }
         Self { rows, cols, table }
     }
-    fn execute<S: SolutionStore>(
-        &self,
-        initial: Bitboard,
-        mut store: S,
-    ) -> Vec<[Bitboard; NUM_PIECES]> {
+    /// All placements as `(piece, bitboard)`, by first cell and then piece.
+    pub(crate) fn placements(&self) -> Vec<(usize, Bitboard)> {
+        self.table
+            .iter()
+            .flat_map(|t| t.iter().enumerate())
+            .flat_map(|(n, v)| v.iter().map(move |&u| (n, u)))
+            .collect()
+    }
+    /// Serialises the placement tables into a binary blob.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer::new(Kind::Default, self.rows, self.cols);
+        w.placements(&self.placements());
+        w.finish()
+    }
+    /// Loads a solver from a blob created by [`Self::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, TableError> {
+        let (mut r, rows, cols) = Reader::new(bytes, Kind::Default)?;
+        let placements = r.placements()?;
+        r.finish()?;
+        Ok(Self::from_placements(rows, cols, &placements))
+    }
+    /// Compiles all solutions with the cells of `initial` filled into a ZDD.
+    /// Cells are filled row by row, so boards build fastest with `cols` as the
+    /// short side.
+    pub fn zdd(&self, initial: Bitboard) -> Zdd {
+        Zdd::build(&self.table, initial)
+    }
+    fn execute(&self, initial: Bitboard) -> Vec<[Bitboard; NUM_PIECES]> {
+        let mut store = AllSolutionStore::default();
         self.backtrack(
             initial,
             (1 << NUM_PIECES) - 1,
             &mut [Bitboard::default(); NUM_PIECES],
             &mut store,
         );
-        store.get_solutions()
+        store.solutions
+    }
+    /// The symmetries of the board, as maps from each cell to its image,
+    /// starting with the identity.
+    pub(crate) fn symmetries(&self) -> Vec<Vec<usize>> {
+        let (rows, cols) = (self.rows, self.cols);
+        let mut ret = Vec::new();
+        for transpose in [false, true] {
+            if transpose && rows != cols {
+                continue;
+            }
+            for (flip_x, flip_y) in [(false, false), (true, false), (false, true), (true, true)] {
+                ret.push(
+                    (0..rows * cols)
+                        .map(|z| {
+                            let (x, y) = (z % cols, z / cols);
+                            let (x, y) = if transpose { (y, x) } else { (x, y) };
+                            let x = if flip_x { cols - 1 - x } else { x };
+                            let y = if flip_y { rows - 1 - y } else { y };
+                            x + y * cols
+                        })
+                        .collect(),
+                );
+            }
+        }
+        ret
+    }
+    pub(crate) fn apply(g: &[usize], mut b: Bitboard) -> Bitboard {
+        let mut ret = 0;
+        while b != 0 {
+            ret |= 1 << g[b.trailing_zeros() as usize];
+            b &= b - 1;
+        }
+        ret
+    }
+    /// Finds one solution per class of solutions equivalent under the board
+    /// symmetries that fix `initial`.
+    ///
+    /// X is only placed at the smallest placement of each orbit under those
+    /// symmetries. No solution is symmetric to itself, since it would have to
+    /// map every piece onto itself and P has no symmetry, so each class is
+    /// found exactly once unless the X placement is fixed by some symmetry;
+    /// the store then keeps only the smallest image.
+    fn execute_unique(&self, initial: Bitboard) -> Vec<[Bitboard; NUM_PIECES]> {
+        let x = Piece::X as usize;
+        let group = self
+            .symmetries()
+            .into_iter()
+            .filter(|g| Self::apply(g, initial) == initial)
+            .collect::<Vec<_>>();
+        let mut store = CanonicalSolutionStore::default();
+        let mut pieces = [Bitboard::default(); NUM_PIECES];
+        for &u in self.table.iter().flat_map(|t| &t[x]) {
+            if initial & u != 0 || group.iter().any(|g| Self::apply(g, u) < u) {
+                continue;
+            }
+            store.symmetries = group[1..]
+                .iter()
+                .filter(|g| Self::apply(g, u) == u)
+                .cloned()
+                .collect();
+            pieces[x] = u;
+            self.backtrack(
+                initial | u,
+                ((1 << NUM_PIECES) - 1) & !(1 << x),
+                &mut pieces,
+                &mut store,
+            );
+        }
+        store.solutions
     }
     fn backtrack<S: SolutionStore>(
         &self,
@@ -165,28 +213,119 @@ impl DefaultSolver {
 impl Solver for DefaultSolver {
     fn solve(&self, initial: Bitboard, unique: bool) -> Vec<[Bitboard; NUM_PIECES]> {
         if unique {
-            self.execute(
-                initial,
-                UniqueSolutionStore::new(|pieces: &[Bitboard; NUM_PIECES]| {
-                    Board(self.represent_solution(pieces))
-                }),
-            )
+            self.execute_unique(initial)
         } else {
-            self.execute(initial, AllSolutionStore::default())
+            self.execute(initial)
         }
     }
     fn represent_solution(&self, solution: &[Bitboard; NUM_PIECES]) -> Vec<Vec<Option<Piece>>> {
-        let mut ret = vec![vec![None; self.cols]; self.rows];
-        for (i, b) in solution.iter().enumerate() {
-            let p = Piece::from_usize(i);
-            for (y, row) in ret.iter_mut().enumerate() {
-                for (x, col) in row.iter_mut().enumerate() {
-                    if b & (1 << (x + y * self.cols)) != 0 {
-                        *col = p;
-                    }
-                }
+        represent(self.rows, self.cols, solution)
+    }
+}
+
+impl ConstrainedSolver for DefaultSolver {
+    fn solve_constrained(
+        &self,
+        initial: Bitboard,
+        constraints: &[Constraint],
+    ) -> Vec<[Bitboard; NUM_PIECES]> {
+        self.solve_pruned(
+            initial,
+            Constraints::new(self.rows, self.cols, false, constraints),
+        )
+    }
+    fn solve_filtered(
+        &self,
+        initial: Bitboard,
+        filter: &PlacementFilter,
+    ) -> Vec<[Bitboard; NUM_PIECES]> {
+        self.solve_pruned(
+            initial,
+            Constraints::filter(self.rows, self.cols, false, filter),
+        )
+    }
+}
+
+impl DefaultSolver {
+    /// Searches the table without the placements that `constraints` rule out.
+    fn solve_pruned(
+        &self,
+        initial: Bitboard,
+        constraints: Constraints,
+    ) -> Vec<[Bitboard; NUM_PIECES]> {
+        let pruned = Self {
+            rows: self.rows,
+            cols: self.cols,
+            table: array::from_fn(|z| {
+                array::from_fn(|i| {
+                    self.table[z][i]
+                        .iter()
+                        .copied()
+                        .filter(|&b| constraints.allows(i, b))
+                        .collect()
+                })
+            }),
+        };
+        let mut store = ConstrainedStore {
+            constraints,
+            solutions: Vec::new(),
+        };
+        pruned.backtrack(
+            initial,
+            (1 << NUM_PIECES) - 1,
+            &mut [Bitboard::default(); NUM_PIECES],
+            &mut store,
+        );
+        store.solutions
+    }
+}
+
+impl SearchTree for DefaultSolver {
+    fn children(&self, current: Bitboard, used: usize, children: &mut Vec<(usize, Bitboard)>) {
+        let target = current.trailing_ones() as usize;
+        for (i, candidates) in self.table[target].iter().enumerate() {
+            if used & (1 << i) == 0 {
+                children.extend(
+                    candidates
+                        .iter()
+                        .filter(|&b| current & b == 0)
+                        .map(|&b| (i, b)),
+                );
             }
         }
-        ret
+    }
+}
+
+impl Estimator for DefaultSolver {
+    fn estimate(&self, initial: Bitboard, probes: usize, seed: u64) -> Estimate {
+        estimator::estimate(self, initial, probes, seed)
+    }
+}
+
+impl RandomSearch for DefaultSolver {
+    fn random_solution(&self, initial: Bitboard, seed: u64) -> Option<[Bitboard; NUM_PIECES]> {
+        sampling::random_solution(self, initial, &mut Rng::new(seed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solve_unique() {
+        let solver = DefaultSolver::new(8, 8);
+        let initial = [27, 28, 35, 36].iter().map(|&p| 1 << p).sum::<u64>();
+        assert_eq!(solver.solve(initial, true).len(), 65);
+
+        // a hole left of the centre is only symmetric under flipping the rows
+        let initial = [26, 27, 34, 35].iter().map(|&p| 1 << p).sum::<u64>();
+        let unique = solver.solve(initial, true);
+        assert_eq!(unique.len(), 1536 / 2);
+        let flip_y = &solver.symmetries()[2];
+        for solution in &unique {
+            let image = solution.map(|b| DefaultSolver::apply(flip_y, b));
+            assert!(!unique.contains(&image));
+        }
     }
 }
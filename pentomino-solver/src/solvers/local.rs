@@ -0,0 +1,541 @@
+use super::{represent, Solver};
+use crate::board::{BoardError, Placement};
+use crate::rng::Rng;
+use crate::shapes::{calculate_shapes, orientation};
+use crate::{Bitboard, Piece, NUM_PIECES};
+use num_traits::FromPrimitive;
+use std::array;
+use std::time::{Duration, Instant};
+
+/// Result of a local search: a complete tiling, or the packing with the most
+/// pieces seen, with `0` for the pieces left out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Packing {
+    Tiling([Bitboard; NUM_PIECES]),
+    Partial([Bitboard; NUM_PIECES]),
+}
+
+impl Packing {
+    pub fn pieces(&self) -> &[Bitboard; NUM_PIECES] {
+        match self {
+            Self::Tiling(pieces) | Self::Partial(pieces) => pieces,
+        }
+    }
+}
+
+/// Result of `RegionSearch::search_region`: a tiling that places the
+/// whole inventory, or the packing with the most pieces seen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegionPacking {
+    Tiling(Vec<Placement>),
+    Partial(Vec<Placement>),
+}
+
+impl RegionPacking {
+    pub fn placements(&self) -> &[Placement] {
+        match self {
+            Self::Tiling(placements) | Self::Partial(placements) => placements,
+        }
+    }
+}
+
+/// Large neighbourhood search over packings of any board and any inventory
+/// of pieces, for boards where exhaustive search takes too long.
+///
+/// The state is a packing without overlaps. Each step clears the pieces from
+/// a small window around a random empty cell and fills the window again with
+/// a short search over the pieces left, keeping the new pieces unless they
+/// are worth less than the old ones, or now and then even so to leave local
+/// minima. Pieces with fewer orientations are worth more, so the ones hard to
+/// fit are placed first, and among fills worth the same the one leaving the
+/// latest cells empty is kept, so empty cells drift together towards the end
+/// of the board until they make room for a piece. The search stops at a
+/// tiling or once the time budget or step limit runs out, returning the
+/// packing with the most pieces seen.
+pub struct RegionSearch {
+    rows: usize,
+    cols: usize,
+    budget: Duration,
+    max_steps: Option<u64>,
+    seed: u64,
+    inventory: [usize; NUM_PIECES],
+    /// Placements of every piece as the cells `x + y * cols`, sorted.
+    placements: [Vec<[usize; 5]>; NUM_PIECES],
+}
+
+/// Probability of keeping a refill with fewer pieces, in percent.
+const NOISE: usize = 2;
+/// Largest distance from the empty cell of the cells of a cleared window.
+const RADIUS: usize = 2;
+/// Nodes searched to fill a cleared window.
+const NODES: usize = 300;
+/// Owner of a cell without a piece.
+const EMPTY: usize = usize::MAX;
+
+impl RegionSearch {
+    pub fn new(rows: usize, cols: usize, budget: Duration, seed: u64) -> Self {
+        let shapes = calculate_shapes();
+        let mut placements: [Vec<[usize; 5]>; NUM_PIECES] = array::from_fn(|_| Vec::new());
+        for (n, shape) in shapes.iter().enumerate() {
+            for s in shape {
+                let (w, h) = s
+                    .iter()
+                    .fold((0, 0), |(xmax, ymax), &(x, y)| (xmax.max(x), ymax.max(y)));
+                if w >= cols || h >= rows {
+                    continue;
+                }
+                for y in 0..rows - h {
+                    for x in 0..cols - w {
+                        let mut cells = [0; 5];
+                        for (z, p) in cells.iter_mut().zip(s) {
+                            *z = (p.0 + x) + (p.1 + y) * cols;
+                        }
+                        cells.sort_unstable();
+                        placements[n].push(cells);
+                    }
+                }
+            }
+        }
+        placements.iter_mut().for_each(|v| v.sort_unstable());
+        Self {
+            rows,
+            cols,
+            budget,
+            max_steps: None,
+            seed,
+            inventory: [1; NUM_PIECES],
+            placements,
+        }
+    }
+    /// Places `inventory[i]` copies of piece `i` instead of one of each.
+    pub fn with_inventory(mut self, inventory: [usize; NUM_PIECES]) -> Self {
+        self.inventory = inventory;
+        self
+    }
+    /// Also stops after `steps` moves, whatever the time budget, which makes
+    /// the result depend only on the seed.
+    pub fn with_max_steps(mut self, steps: u64) -> Self {
+        self.max_steps = Some(steps);
+        self
+    }
+    /// Whether `packing` only uses placements of pieces of the inventory on
+    /// the board, none of them overlapping each other or `holes`, and for a
+    /// tiling whether it places the whole inventory and covers the rest of
+    /// the board.
+    pub fn is_valid_region(&self, holes: &[(usize, usize)], packing: &RegionPacking) -> bool {
+        let shapes = calculate_shapes();
+        let mut covered = vec![false; self.rows * self.cols];
+        for &(x, y) in holes {
+            if x < self.cols && y < self.rows {
+                covered[x + y * self.cols] = true;
+            }
+        }
+        let mut counts = [0; NUM_PIECES];
+        for placement in packing.placements() {
+            let i = placement.piece as usize;
+            counts[i] += 1;
+            if counts[i] > self.inventory[i] || orientation(&shapes, i, &placement.cells).is_none()
+            {
+                return false;
+            }
+            for &(x, y) in &placement.cells {
+                if x >= self.cols || y >= self.rows || covered[x + y * self.cols] {
+                    return false;
+                }
+                covered[x + y * self.cols] = true;
+            }
+        }
+        match packing {
+            RegionPacking::Tiling(_) => counts == self.inventory && covered.iter().all(|&c| c),
+            RegionPacking::Partial(_) => true,
+        }
+    }
+    /// Searches the board with holes at the cells `(x, y)` until a tiling is
+    /// found or the time budget is spent.
+    pub fn search_region(&self, holes: &[(usize, usize)]) -> Result<RegionPacking, BoardError> {
+        let mut filled = vec![false; self.rows * self.cols];
+        for &(x, y) in holes {
+            if x >= self.cols || y >= self.rows {
+                return Err(BoardError::OffBoard(x, y));
+            }
+            filled[x + y * self.cols] = true;
+        }
+        let (tiling, placed) = self.run(&filled);
+        let placements = placed
+            .into_iter()
+            .map(|(i, cells)| Placement {
+                piece: Piece::from_usize(i).unwrap(),
+                cells: cells
+                    .iter()
+                    .map(|z| (z % self.cols, z / self.cols))
+                    .collect(),
+            })
+            .collect();
+        let packing = if tiling {
+            RegionPacking::Tiling(placements)
+        } else {
+            RegionPacking::Partial(placements)
+        };
+        debug_assert!(self.is_valid_region(holes, &packing));
+        Ok(packing)
+    }
+    /// Searches the cells that are not `filled`, returning whether the search
+    /// found a tiling and the pieces placed as `(piece, cells)`.
+    fn run(&self, filled: &[bool]) -> (bool, Vec<(usize, [usize; 5])>) {
+        let start = Instant::now();
+        let mut rng = Rng::new(self.seed);
+        let shapes = calculate_shapes();
+        let placements = (0..NUM_PIECES)
+            .filter(|&i| self.inventory[i] > 0)
+            .flat_map(|i| self.placements[i].iter().map(move |&p| (i, p)))
+            .filter(|(_, p)| p.iter().all(|&z| !filled[z]))
+            .collect::<Vec<_>>();
+        let mut by_cell = vec![Vec::new(); filled.len()];
+        for (q, (_, p)) in placements.iter().enumerate() {
+            p.iter().for_each(|&z| by_cell[z].push(q));
+        }
+        let total = self.inventory.iter().sum::<usize>();
+        let exact = 5 * total == filled.iter().filter(|&&f| !f).count();
+        let mut state = State {
+            placements: &placements,
+            by_cell: &by_cell,
+            left: self.inventory,
+            owner: vec![EMPTY; filled.len()],
+            placed: Vec::new(),
+            weights: array::from_fn(|i| 8 + 8 / shapes[i].len()),
+        };
+        let mut best = Vec::new();
+        for step in 0.. {
+            if state.placed.len() > best.len() {
+                best = state.placed.clone();
+            }
+            if best.len() == total
+                || Some(step) == self.max_steps
+                || (step % 256 == 0 && start.elapsed() >= self.budget)
+            {
+                break;
+            }
+            let uncovered = (0..filled.len())
+                .filter(|&z| !filled[z] && state.owner[z] == EMPTY)
+                .collect::<Vec<_>>();
+            if uncovered.is_empty() {
+                break;
+            }
+            // clear a window around an empty cell and fill it again
+            let z = uncovered[rng.index(uncovered.len())];
+            let (x, y) = (z % self.cols, z / self.cols);
+            let r = 1 + rng.index(RADIUS);
+            let window = (y.saturating_sub(r)..(y + r + 1).min(self.rows))
+                .flat_map(|y| {
+                    (x.saturating_sub(r)..(x + r + 1).min(self.cols)).map(move |x| (x, y))
+                })
+                .map(|(x, y)| x + y * self.cols)
+                .filter(|&z| !filled[z])
+                .collect::<Vec<_>>();
+            let mut removed = window
+                .iter()
+                .map(|&z| state.owner[z])
+                .filter(|&q| q != EMPTY)
+                .collect::<Vec<_>>();
+            removed.sort_unstable();
+            removed.dedup();
+            removed.iter().for_each(|&q| state.take(q));
+            let mut area = window;
+            area.extend(removed.iter().flat_map(|&q| placements[q].1));
+            area.sort_unstable();
+            area.dedup();
+            let mut refill = (0, 0, Vec::new());
+            state.fill(
+                &area,
+                0,
+                0,
+                &mut rng,
+                &mut NODES.clone(),
+                &mut Vec::new(),
+                &mut refill,
+            );
+            let refill = refill.2;
+            // keep the new pieces unless they are worth less, now and then
+            // even so
+            if state.score(&refill) >= state.score(&removed) || rng.index(100) < NOISE {
+                refill.iter().for_each(|&q| state.put(q));
+            } else {
+                removed.iter().for_each(|&q| state.put(q));
+            }
+        }
+        let tiling = exact && best.len() == total;
+        (tiling, best.into_iter().map(|q| placements[q]).collect())
+    }
+}
+
+/// `RegionSearch` on boards of at most 64 cells with one of each piece, whose
+/// packings fit in bitboards.
+pub struct LocalSearchSolver {
+    region: RegionSearch,
+}
+
+impl LocalSearchSolver {
+    pub fn new(rows: usize, cols: usize, budget: Duration, seed: u64) -> Result<Self, BoardError> {
+        if rows == 0 || cols == 0 || rows * cols > 64 {
+            return Err(BoardError::InvalidSize(rows, cols));
+        }
+        Ok(Self {
+            region: RegionSearch::new(rows, cols, budget, seed),
+        })
+    }
+    /// Also stops after `steps` moves, whatever the time budget, which makes
+    /// the result depend only on the seed.
+    pub fn with_max_steps(mut self, steps: u64) -> Self {
+        self.region = self.region.with_max_steps(steps);
+        self
+    }
+    /// Whether `packing` only uses placements of this board's pieces, none of
+    /// them overlapping each other or `initial`, and for a tiling whether they
+    /// cover the rest of the board.
+    pub fn is_valid(&self, initial: Bitboard, packing: &Packing) -> bool {
+        let cols = self.region.cols;
+        let cells = |b: Bitboard| {
+            Self::cells(b)
+                .map(|z| (z % cols, z / cols))
+                .collect::<Vec<_>>()
+        };
+        let placements = packing
+            .pieces()
+            .iter()
+            .enumerate()
+            .filter(|&(_, &b)| b != 0)
+            .map(|(i, &b)| Placement {
+                piece: Piece::from_usize(i).unwrap(),
+                cells: cells(b),
+            })
+            .collect();
+        let holes = cells(initial);
+        match packing {
+            Packing::Tiling(_) => self
+                .region
+                .is_valid_region(&holes, &RegionPacking::Tiling(placements)),
+            Packing::Partial(_) => self
+                .region
+                .is_valid_region(&holes, &RegionPacking::Partial(placements)),
+        }
+    }
+    /// Searches from `initial` until a tiling is found or the time budget is
+    /// spent.
+    pub fn search(&self, initial: Bitboard) -> Packing {
+        let filled = (0..self.region.rows * self.region.cols)
+            .map(|z| initial & (1 << z) != 0)
+            .collect::<Vec<_>>();
+        let (tiling, placed) = self.region.run(&filled);
+        let mut pieces = [0; NUM_PIECES];
+        for (i, cells) in placed {
+            pieces[i] = cells.iter().map(|z| 1 << z).sum();
+        }
+        let packing = if tiling {
+            Packing::Tiling(pieces)
+        } else {
+            Packing::Partial(pieces)
+        };
+        debug_assert!(self.is_valid(initial, &packing));
+        packing
+    }
+    fn cells(b: Bitboard) -> impl Iterator<Item = usize> {
+        let mut b = b;
+        std::iter::from_fn(move || {
+            let z = b.trailing_zeros() as usize;
+            b &= b.wrapping_sub(1);
+            (z < 64).then_some(z)
+        })
+    }
+}
+
+/// A packing without overlaps, with the pieces given as indices into
+/// `placements`.
+struct State<'a> {
+    placements: &'a [(usize, [usize; 5])],
+    /// Placements covering every cell.
+    by_cell: &'a [Vec<usize>],
+    /// Copies of every piece not placed.
+    left: [usize; NUM_PIECES],
+    /// The placement on every cell, or `EMPTY`.
+    owner: Vec<usize>,
+    placed: Vec<usize>,
+    /// Worth of every piece: 16 for X, down to 9 for pieces with eight
+    /// orientations.
+    weights: [usize; NUM_PIECES],
+}
+
+impl State<'_> {
+    fn score(&self, pieces: &[usize]) -> usize {
+        pieces
+            .iter()
+            .map(|&q| self.weights[self.placements[q].0])
+            .sum()
+    }
+    fn fits(&self, q: usize) -> bool {
+        let (i, p) = &self.placements[q];
+        self.left[*i] > 0 && p.iter().all(|&z| self.owner[z] == EMPTY)
+    }
+    fn put(&mut self, q: usize) {
+        let (i, p) = self.placements[q];
+        p.iter().for_each(|&z| self.owner[z] = q);
+        self.left[i] -= 1;
+        self.placed.push(q);
+    }
+    fn take(&mut self, q: usize) {
+        let (i, p) = self.placements[q];
+        p.iter().for_each(|&z| self.owner[z] = EMPTY);
+        self.left[i] += 1;
+        self.placed.retain(|&f| f != q);
+    }
+    /// Places pieces on the empty cells of `area` from its `k`th cell on,
+    /// until `area` is covered or `nodes` runs out, and keeps in `best` the
+    /// pieces placed with the highest score, then with the cells left empty
+    /// the latest in `area`, along with that sum of positions `skipped`.
+    /// Leaves the state as it was.
+    #[allow(clippy::too_many_arguments)]
+    fn fill(
+        &mut self,
+        area: &[usize],
+        k: usize,
+        skipped: usize,
+        rng: &mut Rng,
+        nodes: &mut usize,
+        chosen: &mut Vec<usize>,
+        best: &mut (usize, usize, Vec<usize>),
+    ) -> bool {
+        let Some(k) = (k..area.len()).find(|&k| self.owner[area[k]] == EMPTY) else {
+            let score = self.score(chosen);
+            if (score, skipped) > (best.0, best.1) {
+                *best = (score, skipped, chosen.clone());
+            }
+            return skipped == 0;
+        };
+        if *nodes == 0 {
+            return false;
+        }
+        *nodes -= 1;
+        let mut options = self.by_cell[area[k]]
+            .iter()
+            .copied()
+            .filter(|&q| self.fits(q))
+            .collect::<Vec<_>>();
+        rng.shuffle(&mut options);
+        options.sort_by_key(|&q| std::cmp::Reverse(self.weights[self.placements[q].0]));
+        for q in options {
+            self.put(q);
+            chosen.push(q);
+            let done = self.fill(area, k + 1, skipped, rng, nodes, chosen, best);
+            chosen.pop();
+            self.take(q);
+            if done {
+                return true;
+            }
+        }
+        // leave the cell empty
+        self.fill(area, k + 1, skipped + k + 1, rng, nodes, chosen, best)
+    }
+}
+
+impl Solver for LocalSearchSolver {
+    /// Returns the tiling found within the time budget, if any. A single
+    /// solution is unique already, so `unique` makes no difference.
+    fn solve(&self, initial: Bitboard, _unique: bool) -> Vec<[Bitboard; NUM_PIECES]> {
+        match self.search(initial) {
+            Packing::Tiling(pieces) => vec![pieces],
+            Packing::Partial(_) => Vec::new(),
+        }
+    }
+    fn represent_solution(&self, solution: &[Bitboard; NUM_PIECES]) -> Vec<Vec<Option<Piece>>> {
+        represent(self.region.rows, self.region.cols, solution)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_tiling() {
+        let solver = LocalSearchSolver::new(6, 10, Duration::MAX, 2)
+            .unwrap()
+            .with_max_steps(1000);
+        let packing = solver.search(0);
+        assert!(matches!(packing, Packing::Tiling(_)));
+        assert!(solver.is_valid(0, &packing));
+        assert_eq!(solver.solve(0, false), [*packing.pieces()]);
+    }
+
+    #[test]
+    fn partial_packing() {
+        // holes next to two corners leave those corners isolated
+        let initial = (1 << 1) | (1 << 8) | (1 << 55) | (1 << 62);
+        let solver = LocalSearchSolver::new(8, 8, Duration::MAX, 0)
+            .unwrap()
+            .with_max_steps(2000);
+        let packing = solver.search(initial);
+        assert!(matches!(packing, Packing::Partial(_)));
+        assert!(solver.is_valid(initial, &packing));
+        assert!(packing.pieces().iter().filter(|&&b| b != 0).count() >= 9);
+        assert!(solver.solve(initial, false).is_empty());
+    }
+
+    #[test]
+    fn bitboard_sizes() {
+        for (rows, cols) in [(10, 10), (0, 5), (9, 8)] {
+            assert_eq!(
+                LocalSearchSolver::new(rows, cols, Duration::MAX, 0).err(),
+                Some(BoardError::InvalidSize(rows, cols))
+            );
+        }
+        assert!(LocalSearchSolver::new(8, 8, Duration::MAX, 0).is_ok());
+    }
+
+    #[test]
+    fn regions() {
+        let mut inventory = [0; NUM_PIECES];
+        for p in [Piece::O, Piece::P, Piece::Q, Piece::U, Piece::Y] {
+            inventory[p as usize] = 4;
+        }
+        let solver = RegionSearch::new(10, 10, Duration::MAX, 0)
+            .with_inventory(inventory)
+            .with_max_steps(2000);
+        let packing = solver.search_region(&[]).unwrap();
+        assert!(matches!(packing, RegionPacking::Tiling(_)));
+        assert_eq!(packing.placements().len(), 20);
+        assert!(solver.is_valid_region(&[], &packing));
+
+        // a copy too many, a piece not in the inventory, cells that are not a
+        // piece, and a placement on a hole
+        let mut placements = packing.placements().to_vec();
+        placements.push(placements[0].clone());
+        assert!(!solver.is_valid_region(&[], &RegionPacking::Partial(placements)));
+        let mut placements = packing.placements().to_vec();
+        placements[0].piece = Piece::X;
+        assert!(!solver.is_valid_region(&[], &RegionPacking::Partial(placements)));
+        let mut placements = packing.placements().to_vec();
+        placements[0].cells[0] = (0, 9);
+        placements[0].cells[1] = (9, 0);
+        assert!(!solver.is_valid_region(&[], &RegionPacking::Partial(placements)));
+        let hole = packing.placements()[0].cells[0];
+        assert!(!solver.is_valid_region(&[hole], &packing));
+        let partial = RegionPacking::Partial(packing.placements()[1..].to_vec());
+        assert!(solver.is_valid_region(&[], &partial));
+        assert!(!solver.is_valid_region(
+            &[],
+            &RegionPacking::Tiling(packing.placements()[1..].to_vec())
+        ));
+
+        // fewer pieces than the board holds are all placed, without a tiling
+        let packing = RegionSearch::new(10, 10, Duration::MAX, 0)
+            .with_inventory([1; NUM_PIECES])
+            .with_max_steps(2000)
+            .search_region(&[(3, 3)])
+            .unwrap();
+        assert!(matches!(packing, RegionPacking::Partial(_)));
+        assert_eq!(packing.placements().len(), NUM_PIECES);
+        assert_eq!(
+            solver.search_region(&[(10, 0)]),
+            Err(BoardError::OffBoard(10, 0))
+        );
+    }
+}
@@ -1,26 +1,51 @@
+mod order;
 mod strategy;
 
-use self::strategy::{LargeTableStrategy, SmallTableStrategy, Strategy};
-use super::{SolutionStore, Solver};
+pub use self::order::{CustomOrder, ScanOrder, ScanOrderError};
+
+use self::strategy::{
+    LargeTableStrategy, MostConstrainedStrategy, RegionChecker, SmallTableStrategy, Strategy,
+};
+use super::{represent, SolutionStore, Solver};
+use crate::constraints::{
+    ConstrainedSolver, ConstrainedStore, Constraint, Constraints, PlacementFilter,
+};
+use crate::estimator::{self, Estimate, Estimator, SearchTree};
+use crate::rng::Rng;
+use crate::sampling::{self, RandomSearch};
+use crate::tables::{Kind, Reader, TableError, Writer};
 use crate::{Bitboard, Piece, NUM_PIECES};
-use num_traits::FromPrimitive;
 use std::array;
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::BTreeSet;
 
 const X_INDEX: usize = 9;
 
-#[derive(Default)]
 struct Transformer {
     x_swaps: Vec<(Bitboard, u32)>,
     y_swaps: Vec<(Bitboard, u32)>,
+    square: bool,
+    /// Whether each symmetry other than the identity maps the initial cells
+    /// onto themselves, in the order of `all_images`.
+    fixes: Vec<bool>,
 }
 
 impl Transformer {
-    fn new(rows: usize, cols: usize) -> Self {
-        Self {
+    fn new(rows: usize, cols: usize, initial: Bitboard) -> Self {
+        let mut transformer = Self {
             x_swaps: Self::generate_swaps((0..rows).map(|i| 1 << (cols * i)).sum(), cols, 1),
             y_swaps: Self::generate_swaps((0..cols).map(|i| 1 << i).sum(), rows, cols),
-        }
+            square: rows == cols,
+            fixes: Vec::new(),
+        };
+        transformer.fixes = transformer
+            .all_images(&[initial; NUM_PIECES])
+            .map(|p| p[0] == initial)
+            .collect();
+        transformer
+    }
+    /// Whether every symmetry of the board keeps the initial cells.
+    fn fixes_all(&self) -> bool {
+        self.fixes.iter().all(|&f| f)
     }
     fn flip_x(&self, pieces: &[Bitboard; NUM_PIECES]) -> [Bitboard; NUM_PIECES] {
         array::from_fn(|i| self.x_swaps.iter().fold(pieces[i], Self::delta_swap))
@@ -41,9 +66,9 @@ impl Transformer {
                     mask |= unit << ((i + j) * steps);
                 }
             }
-            ret.push((mask, ((*len + 1) / 2 * steps) as u32));
+            ret.push((mask, (len.div_ceil(2) * steps) as u32));
             stack.push((
-                v.iter().flat_map(|&i| [i, i + (*len + 1) / 2]).collect(),
+                v.iter().flat_map(|&i| [i, i + len.div_ceil(2)]).collect(),
                 len / 2,
             ));
         }
@@ -55,113 +80,213 @@ impl Transformer {
         let t = (x ^ (x >> delta)) & mask;
         x ^ t ^ (t << delta)
     }
-}
-
-#[derive(Default)]
-struct AllSolutionStore {
-    solutions: BTreeSet<[Bitboard; NUM_PIECES]>,
-    transformer: Transformer,
-}
-
-impl AllSolutionStore {
-    fn new(transformer: Transformer) -> Self {
-        Self {
-            transformer,
-            ..Default::default()
-        }
-    }
-}
-
-impl SolutionStore for AllSolutionStore {
-    fn add_solution(&mut self, pieces: &[Bitboard; NUM_PIECES]) {
-        let mut p = *pieces;
-        self.solutions.insert(p);
-        p = self.transformer.flip_x(&p);
-        self.solutions.insert(p);
-        p = self.transformer.flip_y(&p);
-        self.solutions.insert(p);
-        p = self.transformer.flip_x(&p);
-        self.solutions.insert(p);
-    }
-    fn get_solutions(&self) -> Vec<[Bitboard; NUM_PIECES]> {
-        self.solutions.iter().cloned().collect()
-    }
-}
-
-#[derive(Default)]
-struct UniqueSolutionStore<const SQ: bool> {
-    solutions: BTreeMap<[Bitboard; NUM_PIECES], bool>,
-    transformer: Transformer,
-}
-
-impl<const SQ: bool> UniqueSolutionStore<SQ> {
-    fn new(transformer: Transformer) -> Self {
-        Self {
-            transformer,
-            ..Default::default()
-        }
-    }
+    /// Only used on square boards, which hold all twelve pieces only at 8x8.
     fn transpose(pieces: &[Bitboard; NUM_PIECES]) -> [Bitboard; NUM_PIECES] {
         array::from_fn(|i| {
             let mut u = pieces[i];
-            u = Transformer::delta_swap(u, &(0x00AA00AA00AA00AA, 7));
-            u = Transformer::delta_swap(u, &(0x0000CCCC0000CCCC, 14));
-            u = Transformer::delta_swap(u, &(0x00000000F0F0F0F0, 28));
+            u = Self::delta_swap(u, &(0x00AA00AA00AA00AA, 7));
+            u = Self::delta_swap(u, &(0x0000CCCC0000CCCC, 14));
+            u = Self::delta_swap(u, &(0x00000000F0F0F0F0, 28));
             u
         })
     }
+    /// Images of `pieces` under the board's symmetries other than the
+    /// identity that keep the initial cells.
+    fn images(
+        &self,
+        pieces: &[Bitboard; NUM_PIECES],
+    ) -> impl Iterator<Item = [Bitboard; NUM_PIECES]> + '_ {
+        self.all_images(pieces)
+            .zip(&self.fixes)
+            .filter(|&(_, &f)| f)
+            .map(|(p, _)| p)
+    }
+    /// Images of `pieces` under all the board's symmetries other than the
+    /// identity.
+    fn all_images(
+        &self,
+        pieces: &[Bitboard; NUM_PIECES],
+    ) -> impl Iterator<Item = [Bitboard; NUM_PIECES]> + '_ {
+        let flips = |p: [Bitboard; NUM_PIECES]| {
+            let x = self.flip_x(&p);
+            [x, self.flip_y(&p), self.flip_y(&x)]
+        };
+        let transposed = self.square.then(|| Self::transpose(pieces));
+        flips(*pieces).into_iter().chain(
+            transposed
+                .into_iter()
+                .flat_map(move |t| std::iter::once(t).chain(flips(t))),
+        )
+    }
+    /// The lexicographically smallest image of `pieces` under the board's
+    /// symmetries that keep the initial cells.
+    fn canonical(&self, pieces: &[Bitboard; NUM_PIECES]) -> [Bitboard; NUM_PIECES] {
+        self.images(pieces).fold(*pieces, |a, b| a.min(b))
+    }
 }
 
-impl<const SQ: bool> SolutionStore for UniqueSolutionStore<SQ> {
+/// Passes solutions on as soon as the search finds them, without keeping any.
+///
+/// The search only places X at `xs`, so it finds exactly the solutions whose
+/// X lies there, and at least one member of every class of symmetric
+/// solutions. A found solution stands for its class if it is the smallest of
+/// its images that are found too; it is then emitted with all of its images,
+/// or in unique mode just in its canonical form.
+struct SolutionStream<'a> {
+    transformer: Transformer,
+    xs: &'a [Bitboard],
+    unique: bool,
+    emit: &'a mut dyn FnMut(&[Bitboard; NUM_PIECES]),
+}
+
+impl SolutionStore for SolutionStream<'_> {
     fn add_solution(&mut self, pieces: &[Bitboard; NUM_PIECES]) {
-        self.solutions.entry(*pieces).or_insert(true);
-        self.solutions
-            .entry(self.transformer.flip_x(pieces))
-            .or_insert(false);
-        self.solutions
-            .entry(self.transformer.flip_y(pieces))
-            .or_insert(false);
-        if SQ {
-            self.solutions
-                .entry(Self::transpose(pieces))
-                .or_insert(false);
-        }
-    }
-    fn get_solutions(&self) -> Vec<[Bitboard; NUM_PIECES]> {
-        self.solutions
-            .iter()
-            .filter_map(|(k, v)| if *v { Some(k) } else { None })
-            .cloned()
-            .collect()
+        let xs = self.xs;
+        if self
+            .transformer
+            .images(pieces)
+            .any(|p| xs.contains(&p[X_INDEX]) && &p < pieces)
+        {
+            return;
+        }
+        if self.unique {
+            (self.emit)(&self.transformer.canonical(pieces));
+        } else {
+            (self.emit)(pieces);
+            self.transformer
+                .images(pieces)
+                .for_each(|p| (self.emit)(&p));
+        }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum OptimizedSolverType {
-    SmallTable,
-    LargeTable,
+    SmallTable = 0,
+    LargeTable = 1,
+    MostConstrained = 2,
 }
 
+/// X placements, strategy placements and the shapes of the region checker, as
+/// stored in a table blob.
+type Tables = (
+    Vec<Bitboard>,
+    Vec<(usize, Bitboard)>,
+    Vec<(usize, Bitboard)>,
+);
+
 pub struct OptimizedSolver {
     rows: usize,
     cols: usize,
     transposed: bool,
+    solver_type: OptimizedSolverType,
+    order: ScanOrder,
+    /// Cells of the (possibly transposed) board in scan order.
+    cells: Vec<usize>,
     xs: Vec<Bitboard>,
     strategy: Box<dyn Strategy>,
 }
 
 impl OptimizedSolver {
-    pub fn new(mut rows: usize, mut cols: usize, solver_type: OptimizedSolverType) -> Self {
+    pub fn new(rows: usize, cols: usize, solver_type: OptimizedSolverType) -> Self {
+        let order = if rows < cols {
+            ScanOrder::ColumnMajor
+        } else {
+            ScanOrder::RowMajor
+        };
+        Self::with_scan_order(rows, cols, solver_type, order)
+            .expect("built-in scan orders fit any board")
+    }
+    /// Creates a solver whose table strategies fill the cells in `order`.
+    /// `OptimizedSolverType::MostConstrained` picks its own cells and ignores it.
+    /// Fails if `order` is a custom order for another number of cells.
+    pub fn with_scan_order(
+        rows: usize,
+        cols: usize,
+        solver_type: OptimizedSolverType,
+        order: ScanOrder,
+    ) -> Result<Self, ScanOrderError> {
+        Self::build(rows, cols, solver_type, order, None)
+    }
+    fn build(
+        mut rows: usize,
+        mut cols: usize,
+        solver_type: OptimizedSolverType,
+        order: ScanOrder,
+        tables: Option<Tables>,
+    ) -> Result<Self, ScanOrderError> {
         assert!(rows * cols <= 64);
+        let mut cells = order.cells(rows, cols)?;
         let transposed = if rows < cols {
             std::mem::swap(&mut rows, &mut cols);
+            cells
+                .iter_mut()
+                .for_each(|i| *i = *i / rows + *i % rows * cols);
             true
         } else {
             false
         };
+        let (xs, placements) = match tables {
+            Some((xs, placements, shapes)) => (
+                xs,
+                Some((placements, RegionChecker::from_shapes(rows, cols, shapes))),
+            ),
+            None => (Self::x_placements(rows, cols), None),
+        };
+        let strategy = Self::strategy(rows, cols, solver_type, &cells, placements);
+        Ok(Self {
+            rows,
+            cols,
+            transposed,
+            solver_type,
+            order,
+            cells,
+            xs,
+            strategy,
+        })
+    }
+    fn strategy(
+        rows: usize,
+        cols: usize,
+        solver_type: OptimizedSolverType,
+        cells: &[usize],
+        placements: Option<(Vec<(usize, Bitboard)>, RegionChecker)>,
+    ) -> Box<dyn Strategy> {
+        fn create<S: Strategy + 'static>(
+            rows: usize,
+            cols: usize,
+            cells: &[usize],
+            placements: Option<(Vec<(usize, Bitboard)>, RegionChecker)>,
+        ) -> Box<dyn Strategy> {
+            Box::new(match placements {
+                Some((placements, regions)) => {
+                    S::from_placements(rows, cols, cells, placements, regions)
+                }
+                None => S::new(rows, cols, cells),
+            })
+        }
+        match solver_type {
+            OptimizedSolverType::SmallTable => {
+                create::<SmallTableStrategy>(rows, cols, cells, placements)
+            }
+            OptimizedSolverType::LargeTable => {
+                create::<LargeTableStrategy>(rows, cols, cells, placements)
+            }
+            OptimizedSolverType::MostConstrained => {
+                create::<MostConstrainedStrategy>(rows, cols, cells, placements)
+            }
+        }
+    }
+    fn x_placements(rows: usize, cols: usize) -> Vec<Bitboard> {
         let mut xs = Vec::new();
         let v = [(1, 0), (0, 1), (1, 1), (2, 1), (1, 2)]
             .iter()
-            .map(|p| (1 << (p.0 + p.1 * cols)))
+            .map(|p| 1 << (p.0 + p.1 * cols))
             .sum::<u64>();
         for y in 0..(rows - 1) / 2 {
             for x in 0..(cols - 1) / 2 {
@@ -172,68 +297,405 @@ impl OptimizedSolver {
                 }
             }
         }
-        let strategy: Box<dyn Strategy> = match solver_type {
-            OptimizedSolverType::SmallTable => Box::new(SmallTableStrategy::new(rows, cols)),
-            OptimizedSolverType::LargeTable => Box::new(LargeTableStrategy::new(rows, cols)),
+        xs
+    }
+    /// X placements anywhere on the board, for searches that don't rely on
+    /// its symmetries.
+    fn all_x_placements(&self) -> Vec<Bitboard> {
+        let v = [(1, 0), (0, 1), (1, 1), (2, 1), (1, 2)]
+            .iter()
+            .map(|p| 1 << (p.0 + p.1 * self.cols))
+            .sum::<u64>();
+        (0..self.rows - 2)
+            .flat_map(|y| (0..self.cols - 2).map(move |x| v << (x + y * self.cols)))
+            .collect()
+    }
+    /// Serialises the solver's configuration and placement tables into a
+    /// binary blob.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (rows, cols) = if self.transposed {
+            (self.cols, self.rows)
+        } else {
+            (self.rows, self.cols)
+        };
+        let mut w = Writer::new(Kind::Optimized, rows, cols);
+        w.u8(self.solver_type as u8);
+        match &self.order {
+            ScanOrder::RowMajor => w.u8(0),
+            ScanOrder::ColumnMajor => w.u8(1),
+            ScanOrder::Boustrophedon => w.u8(2),
+            ScanOrder::Spiral => w.u8(3),
+            ScanOrder::Custom(order) => {
+                w.u8(4);
+                w.bytes(&order.cells().iter().map(|&i| i as u8).collect::<Vec<_>>());
+            }
+        }
+        w.bitboards(&self.xs);
+        w.placements(&self.strategy.placements());
+        w.placements(&self.strategy.regions().shapes());
+        w.finish()
+    }
+    /// Loads a solver from a blob created by [`Self::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, TableError> {
+        let (mut r, rows, cols) = Reader::new(bytes, Kind::Optimized)?;
+        let solver_type = match r.u8()? {
+            0 => OptimizedSolverType::SmallTable,
+            1 => OptimizedSolverType::LargeTable,
+            2 => OptimizedSolverType::MostConstrained,
+            _ => return Err(TableError::Corrupted),
         };
-        Self {
+        let order = match r.u8()? {
+            0 => ScanOrder::RowMajor,
+            1 => ScanOrder::ColumnMajor,
+            2 => ScanOrder::Boustrophedon,
+            3 => ScanOrder::Spiral,
+            4 => {
+                let cells = r.bytes()?.iter().map(|&i| i as usize).collect();
+                ScanOrder::custom(cells).map_err(|_| TableError::Corrupted)?
+            }
+            _ => return Err(TableError::Corrupted),
+        };
+        let xs = r.bitboards()?;
+        let placements = r.placements()?;
+        let shapes = r.placements()?;
+        r.finish()?;
+        Self::build(
             rows,
             cols,
-            transposed,
-            xs,
-            strategy,
-        }
+            solver_type,
+            order,
+            Some((xs, placements, shapes)),
+        )
+        .map_err(|_| TableError::Corrupted)
+    }
+    /// Tries each of `ScanOrder::candidates()` and keeps the one whose search
+    /// from `initial` has the fewest estimated nodes.
+    pub fn with_auto_scan_order(
+        rows: usize,
+        cols: usize,
+        solver_type: OptimizedSolverType,
+        initial: Bitboard,
+    ) -> Self {
+        ScanOrder::candidates()
+            .into_iter()
+            .map(|order| {
+                Self::with_scan_order(rows, cols, solver_type, order)
+                    .expect("built-in scan orders fit any board")
+            })
+            .map(|solver| (solver.estimate(initial, 1000, 0).nodes.estimate, solver))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, solver)| solver)
+            .expect("no candidate scan orders")
+    }
+    pub fn scan_order(&self) -> &ScanOrder {
+        &self.order
     }
-    fn execute<S: SolutionStore>(
+    /// Calls `f` with each solution as soon as it is found, so solutions
+    /// don't have to be collected. They come in the order the search finds
+    /// them. If some symmetry of the board moves the initial cells, X is
+    /// tried anywhere instead of in one part of the board, which is slower.
+    pub fn solve_with(
         &self,
         initial: Bitboard,
-        mut store: S,
-    ) -> Vec<[Bitboard; NUM_PIECES]> {
+        unique: bool,
+        mut f: impl FnMut(&[Bitboard; NUM_PIECES]),
+    ) {
+        let transformer = Transformer::new(self.rows, self.cols, initial);
+        let all;
+        let xs = if transformer.fixes_all() {
+            &self.xs
+        } else {
+            all = self.all_x_placements();
+            &all
+        };
+        let mut store = SolutionStream {
+            transformer,
+            xs,
+            unique,
+            emit: &mut f,
+        };
         let mut pieces = [Bitboard::default(); NUM_PIECES];
-        for x in &self.xs {
+        for x in xs {
             if initial & x == 0 {
                 pieces[X_INDEX] = *x;
                 self.strategy
                     .backtrack(initial | x, 1 << X_INDEX, &mut pieces, &mut store);
             }
         }
-        store.get_solutions()
     }
 }
 
 impl Solver for OptimizedSolver {
     fn solve(&self, initial: Bitboard, unique: bool) -> Vec<[Bitboard; NUM_PIECES]> {
-        let transformer = Transformer::new(self.rows, self.cols);
-        if unique {
-            if self.rows == self.cols {
-                self.execute(initial, UniqueSolutionStore::<true>::new(transformer))
-            } else {
-                self.execute(initial, UniqueSolutionStore::<false>::new(transformer))
+        let mut solutions = BTreeSet::new();
+        self.solve_with(initial, unique, |pieces| {
+            solutions.insert(*pieces);
+        });
+        solutions.into_iter().collect()
+    }
+    fn represent_solution(&self, solution: &[Bitboard; NUM_PIECES]) -> Vec<Vec<Option<Piece>>> {
+        let ret = represent(self.rows, self.cols, solution);
+        if !self.transposed {
+            return ret;
+        }
+        (0..self.cols)
+            .map(|x| ret.iter().map(|row| row[x]).collect())
+            .collect()
+    }
+}
+
+impl ConstrainedSolver for OptimizedSolver {
+    fn solve_constrained(
+        &self,
+        initial: Bitboard,
+        constraints: &[Constraint],
+    ) -> Vec<[Bitboard; NUM_PIECES]> {
+        let (rows, cols) = self.board_size();
+        self.solve_pruned(
+            initial,
+            Constraints::new(rows, cols, self.transposed, constraints),
+        )
+    }
+    fn solve_filtered(
+        &self,
+        initial: Bitboard,
+        filter: &PlacementFilter,
+    ) -> Vec<[Bitboard; NUM_PIECES]> {
+        let (rows, cols) = self.board_size();
+        self.solve_pruned(
+            initial,
+            Constraints::filter(rows, cols, self.transposed, filter),
+        )
+    }
+}
+
+impl OptimizedSolver {
+    /// Size of the board as given, before any transposition.
+    fn board_size(&self) -> (usize, usize) {
+        if self.transposed {
+            (self.cols, self.rows)
+        } else {
+            (self.rows, self.cols)
+        }
+    }
+    /// Searches without the placements that `constraints` rule out.
+    ///
+    /// Constraints need not be symmetric, so X is tried at every placement
+    /// and no images of the solutions found are added.
+    fn solve_pruned(
+        &self,
+        initial: Bitboard,
+        constraints: Constraints,
+    ) -> Vec<[Bitboard; NUM_PIECES]> {
+        let placements = self
+            .strategy
+            .placements()
+            .into_iter()
+            .filter(|&(i, b)| constraints.allows(i, b))
+            .collect();
+        let regions =
+            RegionChecker::from_shapes(self.rows, self.cols, self.strategy.regions().shapes());
+        let strategy = Self::strategy(
+            self.rows,
+            self.cols,
+            self.solver_type,
+            &self.cells,
+            Some((placements, regions)),
+        );
+        let mut store = ConstrainedStore {
+            constraints,
+            solutions: Vec::new(),
+        };
+        let mut pieces = [Bitboard::default(); NUM_PIECES];
+        for u in self.all_x_placements() {
+            if initial & u == 0 && store.constraints.allows(X_INDEX, u) {
+                pieces[X_INDEX] = u;
+                strategy.backtrack(initial | u, 1 << X_INDEX, &mut pieces, &mut store);
             }
+        }
+        store.solutions
+    }
+}
+
+/// The solver's search tree with X tried at `xs`.
+struct XTree<'a> {
+    solver: &'a OptimizedSolver,
+    xs: &'a [Bitboard],
+}
+
+impl SearchTree for XTree<'_> {
+    fn children(&self, current: Bitboard, used: usize, children: &mut Vec<(usize, Bitboard)>) {
+        if used == 0 {
+            children.extend(
+                self.xs
+                    .iter()
+                    .filter(|&x| current & x == 0)
+                    .map(|&x| (X_INDEX, x)),
+            );
         } else {
-            self.execute(initial, AllSolutionStore::new(transformer))
+            self.solver.strategy.children(current, used, children);
         }
     }
-    fn represent_solution(&self, solution: &[Bitboard; NUM_PIECES]) -> Vec<Vec<Option<Piece>>> {
-        let mut ret = if self.transposed {
-            vec![vec![None; self.rows]; self.cols]
+}
+
+impl SearchTree for OptimizedSolver {
+    fn children(&self, current: Bitboard, used: usize, children: &mut Vec<(usize, Bitboard)>) {
+        XTree {
+            solver: self,
+            xs: &self.xs,
+        }
+        .children(current, used, children)
+    }
+}
+
+impl Estimator for OptimizedSolver {
+    fn estimate(&self, initial: Bitboard, probes: usize, seed: u64) -> Estimate {
+        estimator::estimate(self, initial, probes, seed)
+    }
+}
+
+impl RandomSearch for OptimizedSolver {
+    /// The search only places X in one part of the board, so the solution is
+    /// also mapped by a random symmetry of the board that keeps the initial
+    /// cells. If some symmetry moves them, X is tried anywhere instead.
+    fn random_solution(&self, initial: Bitboard, seed: u64) -> Option<[Bitboard; NUM_PIECES]> {
+        let mut rng = Rng::new(seed);
+        let transformer = Transformer::new(self.rows, self.cols, initial);
+        let all;
+        let xs = if transformer.fixes_all() {
+            &self.xs
         } else {
-            vec![vec![None; self.cols]; self.rows]
+            all = self.all_x_placements();
+            &all
         };
-        for (i, b) in solution.iter().enumerate() {
-            let p = Piece::from_usize(i);
-            for y in 0..self.rows {
-                for x in 0..self.cols {
-                    if b & (1 << (x + y * self.cols)) != 0 {
-                        if self.transposed {
-                            ret[x][y] = p;
-                        } else {
-                            ret[y][x] = p;
-                        }
-                    }
-                }
+        let pieces = sampling::random_solution(&XTree { solver: self, xs }, initial, &mut rng)?;
+        let mut images = vec![pieces];
+        images.extend(transformer.images(&pieces));
+        Some(images[rng.index(images.len())])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solve_with_each_strategy() {
+        let initial = [27, 28, 35, 36].iter().map(|&p| 1 << p).sum::<u64>();
+        for solver_type in [
+            OptimizedSolverType::SmallTable,
+            OptimizedSolverType::LargeTable,
+            OptimizedSolverType::MostConstrained,
+        ] {
+            let solver = OptimizedSolver::new(8, 8, solver_type);
+            assert_eq!(solver.solve(initial, false).len(), 520);
+            assert_eq!(solver.solve(initial, true).len(), 65);
+        }
+    }
+
+    #[test]
+    fn small_boards() {
+        // the board fills up before every piece is placed
+        for solver_type in [
+            OptimizedSolverType::SmallTable,
+            OptimizedSolverType::LargeTable,
+            OptimizedSolverType::MostConstrained,
+        ] {
+            let solver = OptimizedSolver::new(5, 5, solver_type);
+            assert!(solver.solve(0, false).is_empty());
+            assert!(solver.solve(0, true).is_empty());
+            let solver = OptimizedSolver::new(5, 11, solver_type);
+            assert!(solver.solve(0, false).is_empty());
+            // and cells are left over once every piece is placed
+            let solver = OptimizedSolver::new(8, 8, solver_type);
+            assert!(solver.solve(0, false).is_empty());
+            assert!(solver.solve(1 << 27, false).is_empty());
+        }
+    }
+
+    #[test]
+    fn unique_solutions_are_canonical() {
+        let center = [27, 28, 35, 36].iter().map(|&p| 1 << p).sum::<u64>();
+        for (rows, cols, initial) in [(8, 8, center), (3, 20, 0)] {
+            let solver = OptimizedSolver::new(rows, cols, OptimizedSolverType::SmallTable);
+            let transformer = Transformer::new(solver.rows, solver.cols, initial);
+            let all = solver.solve(initial, false);
+            let unique = solver.solve(initial, true);
+            assert_eq!(all.iter().collect::<BTreeSet<_>>().len(), all.len());
+            assert_eq!(
+                unique.into_iter().collect::<BTreeSet<_>>(),
+                all.iter().map(|p| transformer.canonical(p)).collect()
+            );
+        }
+    }
+
+    #[test]
+    fn solve_asymmetric_initial() {
+        // only symmetric under a flip of the rows
+        let initial = [26, 27, 34, 35].iter().map(|&p| 1 << p).sum::<u64>();
+        let solver = OptimizedSolver::new(8, 8, OptimizedSolverType::SmallTable);
+        let transformer = Transformer::new(8, 8, initial);
+        let all = solver.solve(initial, false);
+        assert!(all.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(
+            all.iter().collect::<BTreeSet<_>>(),
+            solver.solve_constrained(initial, &[]).iter().collect()
+        );
+        let unique = solver.solve(initial, true);
+        assert!(unique.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(
+            unique.into_iter().collect::<BTreeSet<_>>(),
+            all.iter().map(|p| transformer.canonical(p)).collect()
+        );
+    }
+
+    #[test]
+    fn random_solutions_keep_initial_cells() {
+        // only symmetric under a flip of the rows
+        let initial = [26, 27, 34, 35].iter().map(|&p| 1 << p).sum::<u64>();
+        let solver = OptimizedSolver::new(8, 8, OptimizedSolverType::SmallTable);
+        for seed in 0..40 {
+            let pieces = solver.random_solution(initial, seed).unwrap();
+            assert!(pieces
+                .iter()
+                .all(|b| b.count_ones() == 5 && b & initial == 0));
+            assert_eq!(pieces.iter().fold(initial, |a, b| a | b), u64::MAX);
+        }
+    }
+
+    #[test]
+    fn solve_with_each_scan_order() {
+        let mut orders = ScanOrder::candidates().to_vec();
+        orders.push(ScanOrder::custom((0..60).rev().collect()).unwrap());
+        for order in orders {
+            for solver_type in [
+                OptimizedSolverType::SmallTable,
+                OptimizedSolverType::LargeTable,
+            ] {
+                let solver =
+                    OptimizedSolver::with_scan_order(3, 20, solver_type, order.clone()).unwrap();
+                assert_eq!(solver.solve(0, false).len(), 8);
+                assert_eq!(solver.solve(0, true).len(), 2);
             }
         }
-        ret
+        let initial = [27, 28, 35, 36].iter().map(|&p| 1 << p).sum::<u64>();
+        let solver = OptimizedSolver::with_scan_order(
+            8,
+            8,
+            OptimizedSolverType::SmallTable,
+            ScanOrder::Spiral,
+        )
+        .unwrap();
+        assert_eq!(solver.solve(initial, true).len(), 65);
+        assert_eq!(
+            OptimizedSolver::with_scan_order(
+                6,
+                10,
+                OptimizedSolverType::SmallTable,
+                ScanOrder::custom((0..64).collect()).unwrap(),
+            )
+            .err(),
+            Some(ScanOrderError::WrongSize(64, 60))
+        );
     }
 }
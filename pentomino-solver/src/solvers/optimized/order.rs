@@ -0,0 +1,156 @@
+use derive_more::Display;
+
+#[derive(Debug, Display, Clone, PartialEq, Eq)]
+pub enum ScanOrderError {
+    #[display(fmt = "custom scan order is not a permutation of its cells")]
+    NotAPermutation,
+    #[display(fmt = "custom scan order has {} cells, but the board has {}", _0, _1)]
+    WrongSize(usize, usize),
+}
+
+impl std::error::Error for ScanOrderError {}
+
+/// Order in which the table-based strategies fill the cells of the board.
+///
+/// Cells are numbered `x + y * cols` on the board as passed to the solver.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ScanOrder {
+    RowMajor,
+    ColumnMajor,
+    /// Row by row, alternating left-to-right and right-to-left.
+    Boustrophedon,
+    /// Clockwise from the top-left corner towards the centre.
+    Spiral,
+    /// Any permutation of the cell indices, built with `ScanOrder::custom`.
+    Custom(CustomOrder),
+}
+
+/// A permutation of `0..n`, the cells of a board of `n` cells.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "Vec<usize>", into = "Vec<usize>")
+)]
+pub struct CustomOrder(Vec<usize>);
+
+impl CustomOrder {
+    pub fn cells(&self) -> &[usize] {
+        &self.0
+    }
+}
+
+impl TryFrom<Vec<usize>> for CustomOrder {
+    type Error = ScanOrderError;
+
+    fn try_from(cells: Vec<usize>) -> Result<Self, Self::Error> {
+        let mut sorted = cells.clone();
+        sorted.sort_unstable();
+        if !sorted.into_iter().eq(0..cells.len()) {
+            return Err(ScanOrderError::NotAPermutation);
+        }
+        Ok(Self(cells))
+    }
+}
+
+impl From<CustomOrder> for Vec<usize> {
+    fn from(order: CustomOrder) -> Self {
+        order.0
+    }
+}
+
+impl ScanOrder {
+    /// The built-in orders tried when choosing one automatically.
+    pub fn candidates() -> [ScanOrder; 4] {
+        [
+            Self::RowMajor,
+            Self::ColumnMajor,
+            Self::Boustrophedon,
+            Self::Spiral,
+        ]
+    }
+    /// The order filling the cells `x + y * cols` as listed, which must list
+    /// each cell once.
+    pub fn custom(cells: Vec<usize>) -> Result<Self, ScanOrderError> {
+        cells.try_into().map(Self::Custom)
+    }
+    /// Cell indices in the order they are filled. Fails for a custom order
+    /// of another number of cells.
+    pub fn cells(&self, rows: usize, cols: usize) -> Result<Vec<usize>, ScanOrderError> {
+        Ok(match self {
+            Self::RowMajor => (0..rows * cols).collect(),
+            Self::ColumnMajor => (0..cols)
+                .flat_map(|x| (0..rows).map(move |y| x + y * cols))
+                .collect(),
+            Self::Boustrophedon => (0..rows)
+                .flat_map(|y| {
+                    (0..cols).map(move |x| if y % 2 == 0 { x } else { cols - 1 - x } + y * cols)
+                })
+                .collect(),
+            Self::Spiral => {
+                let mut ret = Vec::with_capacity(rows * cols);
+                let (mut top, mut bottom, mut left, mut right) = (0, rows, 0, cols);
+                while top < bottom && left < right {
+                    ret.extend((left..right).map(|x| x + top * cols));
+                    ret.extend((top + 1..bottom).map(|y| right - 1 + y * cols));
+                    if top + 1 < bottom {
+                        ret.extend((left..right - 1).rev().map(|x| x + (bottom - 1) * cols));
+                    }
+                    if left + 1 < right {
+                        ret.extend((top + 1..bottom - 1).rev().map(|y| left + y * cols));
+                    }
+                    (top, bottom, left, right) = (top + 1, bottom - 1, left + 1, right - 1);
+                }
+                ret
+            }
+            Self::Custom(order) if order.0.len() != rows * cols => {
+                return Err(ScanOrderError::WrongSize(order.0.len(), rows * cols))
+            }
+            Self::Custom(order) => order.0.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells() {
+        assert_eq!(
+            ScanOrder::RowMajor.cells(2, 3).unwrap(),
+            vec![0, 1, 2, 3, 4, 5]
+        );
+        assert_eq!(
+            ScanOrder::ColumnMajor.cells(2, 3).unwrap(),
+            vec![0, 3, 1, 4, 2, 5]
+        );
+        assert_eq!(
+            ScanOrder::Boustrophedon.cells(2, 3).unwrap(),
+            vec![0, 1, 2, 5, 4, 3]
+        );
+        assert_eq!(
+            ScanOrder::Spiral.cells(3, 4).unwrap(),
+            vec![0, 1, 2, 3, 7, 11, 10, 9, 8, 4, 5, 6]
+        );
+        assert_eq!(ScanOrder::Spiral.cells(3, 1).unwrap(), vec![0, 1, 2]);
+        assert_eq!(ScanOrder::Spiral.cells(1, 3).unwrap(), vec![0, 1, 2]);
+
+        let order = ScanOrder::custom(vec![2, 0, 1]).unwrap();
+        assert_eq!(order.cells(1, 3), Ok(vec![2, 0, 1]));
+        assert_eq!(order.cells(2, 3), Err(ScanOrderError::WrongSize(3, 6)));
+        assert_eq!(
+            ScanOrder::custom(vec![0, 2]),
+            Err(ScanOrderError::NotAPermutation)
+        );
+        assert_eq!(
+            ScanOrder::custom(vec![1, 1]),
+            Err(ScanOrderError::NotAPermutation)
+        );
+    }
+}
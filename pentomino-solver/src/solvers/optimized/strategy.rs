@@ -3,9 +3,9 @@ use crate::solvers::SolutionStore;
 use crate::{Bitboard, NUM_PIECES};
 use std::array;
 
-type HoleCheckers = [[(Bitboard, Bitboard); 2]; 64];
+type HoleCheckers = Vec<[(Bitboard, Bitboard); 2]>;
 
-fn hole_checkers(rows: usize, cols: usize) -> HoleCheckers {
+fn hole_checkers(rows: usize, cols: usize, scan: &Scan) -> HoleCheckers {
     let mut h = Vec::new();
     for y in 0..rows {
         for x in 0..cols {
@@ -19,13 +19,226 @@ fn hole_checkers(rows: usize, cols: usize) -> HoleCheckers {
             h.push((u | (1 << (x + y * cols)), u));
         }
     }
-    array::from_fn(|i| [h[(i + 1) % h.len()], h[(i + cols - 1) % h.len()]])
+    let (order, n) = (&scan.order, h.len());
+    (0..n)
+        .map(|i| {
+            let k = scan.rank[i];
+            [h[order[(k + 1) % n]], h[order[(k + scan.line - 1) % n]]]
+        })
+        .collect()
+}
+
+/// Order in which the table strategies fill the cells: the target of each
+/// step is the first empty cell in `order`.
+struct Scan {
+    order: Vec<usize>,
+    rank: [usize; 64],
+    line: usize,
+}
+
+impl Scan {
+    fn new(cols: usize, order: &[usize]) -> Self {
+        let mut rank = [usize::MAX; 64];
+        for (k, &i) in order.iter().enumerate() {
+            rank[i] = k;
+        }
+        // length of the first straight run of adjacent cells
+        let step = |k: usize| {
+            let (a, b, cols) = (order[k] as isize, order[k + 1] as isize, cols as isize);
+            (b % cols - a % cols, b / cols - a / cols)
+        };
+        let mut line = 1;
+        while line < order.len() {
+            let d = step(line - 1);
+            if d.0.abs() + d.1.abs() != 1 || (line > 1 && d != step(line - 2)) {
+                break;
+            }
+            line += 1;
+        }
+        Self {
+            order: order.to_vec(),
+            rank,
+            line,
+        }
+    }
+    /// Advances `k` to the first empty cell and returns it, or `None` once
+    /// the board is full, which happens before every piece is placed on
+    /// boards of fewer than 60 cells.
+    #[inline]
+    fn target(&self, current: Bitboard, k: &mut usize) -> Option<usize> {
+        while *k < self.order.len() && current & (1 << self.order[*k]) != 0 {
+            *k += 1;
+        }
+        self.order.get(*k).copied()
+    }
+    /// The cell of `u` that comes first in the scan order.
+    fn first(&self, mut u: Bitboard) -> usize {
+        let mut ret = u.trailing_zeros() as usize;
+        while u != 0 {
+            let i = u.trailing_zeros() as usize;
+            if self.rank[i] < self.rank[ret] {
+                ret = i;
+            }
+            u &= u - 1;
+        }
+        ret
+    }
+}
+
+/// Placements of all pieces except X, leaving out the ones `Checker` rules out.
+fn placements(rows: usize, cols: usize) -> Vec<(usize, Bitboard)> {
+    let mut ret = Vec::new();
+    let checker = Checker::new(rows, cols);
+    let shapes = crate::shapes::calculate_shapes();
+    for (i, shape) in shapes.iter().enumerate() {
+        if i == X_INDEX {
+            continue;
+        }
+        for s in shape {
+            if s.iter().any(|&(x, y)| x >= cols || y >= rows) {
+                continue;
+            }
+            let v = s.iter().map(|p| 1 << (p.0 + p.1 * cols)).sum::<u64>();
+            let (w, h) = s
+                .iter()
+                .fold((0, 0), |(xmax, ymax), &(x, y)| (xmax.max(x), ymax.max(y)));
+            for y in 0..rows - h {
+                for x in 0..cols - w {
+                    let u = v << (x + y * cols);
+                    if checker.check_corner_space(u) || checker.check_hole(u) {
+                        continue;
+                    }
+                    ret.push((i, u));
+                }
+            }
+        }
+    }
+    ret
+}
+
+/// Detects empty regions that can no longer be tiled, using bitboard flood fill
+/// from the cells around the last placement.
+pub(super) struct RegionChecker {
+    cols: usize,
+    board: Bitboard,
+    not_first_col: Bitboard,
+    not_last_col: Bitboard,
+    shapes: Vec<(Bitboard, usize)>,
+}
+
+impl RegionChecker {
+    fn new(rows: usize, cols: usize) -> Self {
+        let mut shapes = Vec::new();
+        for (i, shape) in crate::shapes::calculate_shapes().iter().enumerate() {
+            for s in shape {
+                if s.iter().any(|&(x, y)| x >= cols || y >= rows) {
+                    continue;
+                }
+                let v = s.iter().map(|p| 1 << (p.0 + p.1 * cols)).sum::<Bitboard>();
+                shapes.push((i, v >> s[0].0));
+            }
+        }
+        Self::from_shapes(rows, cols, shapes)
+    }
+    /// Builds the checker from the orientations of every piece that fit the
+    /// board, as `(piece, cells)` shifted to the lowest cell, as returned by
+    /// [`Self::shapes`].
+    pub(super) fn from_shapes(rows: usize, cols: usize, shapes: Vec<(usize, Bitboard)>) -> Self {
+        let board = if rows * cols == 64 {
+            !0
+        } else {
+            (1 << (rows * cols)) - 1
+        };
+        let first_col = (0..rows).map(|i| 1 << (i * cols)).sum::<Bitboard>();
+        let mut shapes = shapes
+            .into_iter()
+            .map(|(i, v)| (v, 1 << i))
+            .collect::<Vec<_>>();
+        shapes.sort_unstable();
+        Self {
+            cols,
+            board,
+            not_first_col: board & !first_col,
+            not_last_col: board & !(first_col << (cols - 1)),
+            shapes,
+        }
+    }
+    pub(super) fn shapes(&self) -> Vec<(usize, Bitboard)> {
+        self.shapes
+            .iter()
+            .map(|&(v, mask)| (mask.trailing_zeros() as usize, v))
+            .collect()
+    }
+    #[inline]
+    fn expand(&self, u: Bitboard) -> Bitboard {
+        u | ((u << 1) & self.not_first_col)
+            | ((u >> 1) & self.not_last_col)
+            | (u << self.cols)
+            | (u >> self.cols)
+    }
+    /// Returns `true` if placing `placed` (giving `next`) leaves an empty region
+    /// whose size is not a multiple of five, or a five-cell region that matches
+    /// none of the pieces not in `used`.
+    fn check(&self, next: Bitboard, placed: Bitboard, used: usize) -> bool {
+        let free = !next & self.board;
+        let mut seeds = self.expand(placed) & free;
+        while seeds != 0 {
+            let mut region = seeds & seeds.wrapping_neg();
+            loop {
+                let expanded = self.expand(region) & free;
+                if expanded == region {
+                    break;
+                }
+                region = expanded;
+            }
+            let size = region.count_ones();
+            if !size.is_multiple_of(5) {
+                return true;
+            }
+            if size == 5 {
+                let pattern = region >> region.trailing_zeros();
+                let start = self.shapes.partition_point(|&(v, _)| v < pattern);
+                if !self.shapes[start..]
+                    .iter()
+                    .take_while(|&&(v, _)| v == pattern)
+                    .any(|&(_, mask)| mask & !used != 0)
+                {
+                    return true;
+                }
+            }
+            seeds &= !region;
+        }
+        false
+    }
 }
 
 pub(super) trait Strategy {
-    fn new(rows: usize, cols: usize) -> Self
+    fn new(rows: usize, cols: usize, order: &[usize]) -> Self
+    where
+        Self: Sized,
+    {
+        Self::from_placements(
+            rows,
+            cols,
+            order,
+            placements(rows, cols),
+            RegionChecker::new(rows, cols),
+        )
+    }
+    /// Builds the strategy from stored tables, without computing the shapes
+    /// of the pieces again.
+    fn from_placements(
+        rows: usize,
+        cols: usize,
+        order: &[usize],
+        placements: Vec<(usize, Bitboard)>,
+        regions: RegionChecker,
+    ) -> Self
     where
         Self: Sized;
+    /// The placements the strategy was built from, in table order.
+    fn placements(&self) -> Vec<(usize, Bitboard)>;
+    fn regions(&self) -> &RegionChecker;
     fn backtrack(
         &self,
         current: Bitboard,
@@ -33,58 +246,31 @@ pub(super) trait Strategy {
         pieces: &mut [Bitboard; NUM_PIECES],
         store: &mut dyn SolutionStore,
     );
+    fn children(&self, current: Bitboard, used: usize, children: &mut Vec<(usize, Bitboard)>);
 }
 
 pub(super) struct SmallTableStrategy {
     table: [[Vec<Bitboard>; NUM_PIECES]; 64],
+    scan: Scan,
     holes: HoleCheckers,
+    regions: RegionChecker,
 }
 
-impl Strategy for SmallTableStrategy {
-    fn new(rows: usize, cols: usize) -> Self {
-        let mut table = array::from_fn(|_| array::from_fn(|_| Vec::new()));
-        let checker = Checker::new(rows, cols);
-        let shapes = crate::shapes::calculate_shapes();
-        for (i, shape) in shapes.iter().enumerate() {
-            if i == X_INDEX {
-                continue;
-            }
-            for s in shape {
-                if s.iter().any(|&(x, y)| x >= cols || y >= rows) {
-                    continue;
-                }
-                let v = s.iter().map(|p| (1 << (p.0 + p.1 * cols))).sum::<u64>();
-                let (w, h) = s
-                    .iter()
-                    .fold((0, 0), |(xmax, ymax), &(x, y)| (xmax.max(x), ymax.max(y)));
-                for y in 0..rows - h {
-                    for x in 0..cols - w {
-                        let offset = x + y * cols;
-                        let u = v << offset;
-                        if checker.check_corner_space(u) || checker.check_hole(u) {
-                            continue;
-                        }
-                        table[s[0].0 + offset][i].push(u);
-                    }
-                }
-            }
-        }
-        Self {
-            table,
-            holes: hole_checkers(rows, cols),
-        }
-    }
-    fn backtrack(
+impl SmallTableStrategy {
+    fn search(
         &self,
         current: Bitboard,
         used: usize,
+        mut k: usize,
         pieces: &mut [Bitboard; NUM_PIECES],
         store: &mut dyn SolutionStore,
     ) {
         if used == (1 << NUM_PIECES) - 1 {
             return store.add_solution(pieces);
         }
-        let target = current.trailing_ones() as usize;
+        let Some(target) = self.scan.target(current, &mut k) else {
+            return;
+        };
 
         let mut u = !used & ((1 << NUM_PIECES) - 1);
         while u != 0 {
@@ -93,11 +279,74 @@ impl Strategy for SmallTableStrategy {
             for b in &self.table[target][i] {
                 if current & b == 0 {
                     let next = current | b;
-                    if self.holes[target].iter().any(|&(u, v)| next & u == v) {
+                    if self.holes[target].iter().any(|&(u, v)| next & u == v)
+                        || self.regions.check(next, *b, used)
+                    {
                         continue;
                     }
                     pieces[i] = *b;
-                    self.backtrack(next, used, pieces, store);
+                    self.search(next, used, k, pieces, store);
+                }
+            }
+            u &= u - 1;
+        }
+    }
+}
+
+impl Strategy for SmallTableStrategy {
+    fn from_placements(
+        rows: usize,
+        cols: usize,
+        order: &[usize],
+        placements: Vec<(usize, Bitboard)>,
+        regions: RegionChecker,
+    ) -> Self {
+        let scan = Scan::new(cols, order);
+        let mut table: [[Vec<Bitboard>; NUM_PIECES]; 64] =
+            array::from_fn(|_| array::from_fn(|_| Vec::new()));
+        for (i, u) in placements {
+            table[scan.first(u)][i].push(u);
+        }
+        Self {
+            table,
+            holes: hole_checkers(rows, cols, &scan),
+            scan,
+            regions,
+        }
+    }
+    fn regions(&self) -> &RegionChecker {
+        &self.regions
+    }
+    fn placements(&self) -> Vec<(usize, Bitboard)> {
+        self.table
+            .iter()
+            .flat_map(|t| t.iter().enumerate())
+            .flat_map(|(i, v)| v.iter().map(move |&u| (i, u)))
+            .collect()
+    }
+    fn backtrack(
+        &self,
+        current: Bitboard,
+        used: usize,
+        pieces: &mut [Bitboard; NUM_PIECES],
+        store: &mut dyn SolutionStore,
+    ) {
+        self.search(current, used, 0, pieces, store);
+    }
+    fn children(&self, current: Bitboard, used: usize, children: &mut Vec<(usize, Bitboard)>) {
+        let Some(target) = self.scan.target(current, &mut 0) else {
+            return;
+        };
+        let mut u = !used & ((1 << NUM_PIECES) - 1);
+        while u != 0 {
+            let i = u.trailing_zeros() as usize;
+            for &b in &self.table[target][i] {
+                let next = current | b;
+                if current & b == 0
+                    && !self.holes[target].iter().any(|&(u, v)| next & u == v)
+                    && !self.regions.check(next, b, used | (1 << i))
+                {
+                    children.push((i, b));
                 }
             }
             u &= u - 1;
@@ -105,68 +354,330 @@ impl Strategy for SmallTableStrategy {
     }
 }
 
+/// Number of pieces in each half of the used set, indexed separately by
+/// `LargeTableStrategy`.
+const HALF: usize = NUM_PIECES / 2;
+
+/// Looks up the candidates for a cell by the set of used pieces.
+///
+/// The used set is split into two halves of `HALF` pieces. For each cell and
+/// each subset of either half, the placements of the pieces of that half which
+/// are not in the subset are stored as one contiguous slice of a shared
+/// buffer, so a lookup walks two slices and each placement is stored
+/// `2^(HALF - 1)` times instead of once per used set.
 pub(super) struct LargeTableStrategy {
-    table: [Vec<Vec<(usize, Bitboard)>>; 64],
+    offsets: Vec<u32>,
+    pieces: Vec<u8>,
+    boards: Vec<Bitboard>,
+    scan: Scan,
     holes: HoleCheckers,
+    regions: RegionChecker,
 }
 
-impl Strategy for LargeTableStrategy {
-    fn new(rows: usize, cols: usize) -> Self {
-        let mut table = array::from_fn(|_| vec![Vec::new(); 1 << NUM_PIECES]);
-        let checker = Checker::new(rows, cols);
-        let shapes = crate::shapes::calculate_shapes();
-        for (i, shape) in shapes.iter().enumerate() {
-            if i == X_INDEX {
-                continue;
-            }
-            for s in shape {
-                if s.iter().any(|&(x, y)| x >= cols || y >= rows) {
+impl LargeTableStrategy {
+    /// Slots of the two slices holding the candidates for `target`.
+    #[inline]
+    fn slots(target: usize, used: usize) -> [usize; 2] {
+        let base = target << (HALF + 1);
+        [
+            base + (used & ((1 << HALF) - 1)),
+            base + (1 << HALF) + (used >> HALF),
+        ]
+    }
+    #[inline]
+    fn candidates(
+        &self,
+        target: usize,
+        used: usize,
+    ) -> impl Iterator<Item = (usize, Bitboard)> + '_ {
+        Self::slots(target, used).into_iter().flat_map(move |slot| {
+            let range = self.offsets[slot] as usize..self.offsets[slot + 1] as usize;
+            self.pieces[range.clone()]
+                .iter()
+                .zip(&self.boards[range])
+                .map(|(&i, &b)| (i as usize, b))
+        })
+    }
+    fn search(
+        &self,
+        current: Bitboard,
+        used: usize,
+        mut k: usize,
+        pieces: &mut [Bitboard; NUM_PIECES],
+        store: &mut dyn SolutionStore,
+    ) {
+        if used == (1 << NUM_PIECES) - 1 {
+            return store.add_solution(pieces);
+        }
+        let Some(target) = self.scan.target(current, &mut k) else {
+            return;
+        };
+        for (i, b) in self.candidates(target, used) {
+            if current & b == 0 {
+                let next = current | b;
+                let used = used | (1 << i);
+                if self.holes[target].iter().any(|&(u, v)| next & u == v)
+                    || self.regions.check(next, b, used)
+                {
                     continue;
                 }
-                let v = s.iter().map(|p| (1 << (p.0 + p.1 * cols))).sum::<u64>();
-                let (w, h) = s
-                    .iter()
-                    .fold((0, 0), |(xmax, ymax), &(x, y)| (xmax.max(x), ymax.max(y)));
-                for y in 0..rows - h {
-                    for x in 0..cols - w {
-                        let offset = x + y * cols;
-                        let u = v << offset;
-                        if checker.check_corner_space(u) || checker.check_hole(u) {
-                            continue;
-                        }
-                        for j in 0..(1 << NUM_PIECES) {
-                            if (j & (1 << i)) == 0 {
-                                table[s[0].0 + offset][j].push((i, u));
-                            }
+                pieces[i] = b;
+                self.search(next, used, k, pieces, store);
+            }
+        }
+    }
+}
+
+impl Strategy for LargeTableStrategy {
+    fn from_placements(
+        rows: usize,
+        cols: usize,
+        order: &[usize],
+        placements: Vec<(usize, Bitboard)>,
+        regions: RegionChecker,
+    ) -> Self {
+        let scan = Scan::new(cols, order);
+        // every placement is in half of the slices of its half
+        let len = placements.len() << (HALF - 1);
+        let mut by_cell = vec![Vec::new(); 64];
+        for (i, u) in placements {
+            by_cell[scan.first(u)].push((i, u));
+        }
+        let mut offsets = Vec::with_capacity((64 << (HALF + 1)) + 1);
+        let (mut pieces, mut boards) = (Vec::with_capacity(len), Vec::with_capacity(len));
+        for entries in &by_cell {
+            for (half, shift) in [(0, 0), (1, HALF)] {
+                for subset in 0..(1 << HALF) {
+                    offsets.push(boards.len() as u32);
+                    for &(i, u) in entries {
+                        if (i >= HALF) as usize == half && subset & (1 << (i - shift)) == 0 {
+                            pieces.push(i as u8);
+                            boards.push(u);
                         }
                     }
                 }
             }
         }
+        offsets.push(boards.len() as u32);
         Self {
-            table,
-            holes: hole_checkers(rows, cols),
+            offsets,
+            pieces,
+            boards,
+            holes: hole_checkers(rows, cols, &scan),
+            scan,
+            regions,
         }
     }
+    fn regions(&self) -> &RegionChecker {
+        &self.regions
+    }
+    fn placements(&self) -> Vec<(usize, Bitboard)> {
+        // with nothing used, the two slices of a cell hold all its placements
+        (0..64)
+            .flat_map(|target| self.candidates(target, 0))
+            .collect()
+    }
     fn backtrack(
         &self,
         current: Bitboard,
         used: usize,
         pieces: &mut [Bitboard; NUM_PIECES],
         store: &mut dyn SolutionStore,
+    ) {
+        self.search(current, used, 0, pieces, store);
+    }
+    fn children(&self, current: Bitboard, used: usize, children: &mut Vec<(usize, Bitboard)>) {
+        let Some(target) = self.scan.target(current, &mut 0) else {
+            return;
+        };
+        for (i, b) in self.candidates(target, used) {
+            let next = current | b;
+            if current & b == 0
+                && !self.holes[target].iter().any(|&(u, v)| next & u == v)
+                && !self.regions.check(next, b, used | (1 << i))
+            {
+                children.push((i, b));
+            }
+        }
+    }
+}
+
+/// Branches on the empty cell or the remaining piece with the fewest legal
+/// placements, instead of always filling the first empty cell.
+///
+/// Legal placements are tracked as a bitset over all placements, so the count
+/// for a cell or piece is a popcount of its precomputed row masked by it.
+pub(super) struct MostConstrainedStrategy {
+    placements: Vec<(usize, Bitboard)>,
+    words: usize,
+    cells: Vec<u64>,
+    pieces: Vec<u64>,
+    board: Bitboard,
+    regions: RegionChecker,
+}
+
+impl MostConstrainedStrategy {
+    fn cell_row(&self, cell: usize) -> &[u64] {
+        &self.cells[cell * self.words..(cell + 1) * self.words]
+    }
+    fn piece_row(&self, piece: usize) -> &[u64] {
+        &self.pieces[piece * self.words..(piece + 1) * self.words]
+    }
+    fn live(&self, current: Bitboard, used: usize, live: &mut [u64]) {
+        live.fill(0);
+        for (j, &(i, b)) in self.placements.iter().enumerate() {
+            if used & (1 << i) == 0 && current & b == 0 {
+                live[j / 64] |= 1 << (j % 64);
+            }
+        }
+    }
+    fn count(row: &[u64], live: &[u64]) -> u32 {
+        row.iter()
+            .zip(live)
+            .map(|(r, l)| (r & l).count_ones())
+            .sum()
+    }
+    /// Returns the row of placements to branch on, or `None` if some empty
+    /// cell or remaining piece has no legal placement left.
+    fn select(&self, current: Bitboard, used: usize, live: &[u64]) -> Option<&[u64]> {
+        let mut best: Option<(u32, &[u64])> = None;
+        let mut update = |row| {
+            let n = Self::count(row, live);
+            if best.is_none_or(|(m, _)| n < m) {
+                best = Some((n, row));
+            }
+            n
+        };
+        let mut empty = !current & self.board;
+        while empty != 0 {
+            match update(self.cell_row(empty.trailing_zeros() as usize)) {
+                0 => return None,
+                1 => break,
+                _ => {}
+            }
+            empty &= empty - 1;
+        }
+        let mut u = !used & ((1 << NUM_PIECES) - 1);
+        while u != 0 {
+            update(self.piece_row(u.trailing_zeros() as usize));
+            u &= u - 1;
+        }
+        best.and_then(|(n, row)| if n > 0 { Some(row) } else { None })
+    }
+    /// Searches from the node whose legal placements are the first `words`
+    /// of `buffer`, using the rest for the nodes below it.
+    fn search(
+        &self,
+        current: Bitboard,
+        used: usize,
+        buffer: &mut [u64],
+        pieces: &mut [Bitboard; NUM_PIECES],
+        store: &mut dyn SolutionStore,
     ) {
         if used == (1 << NUM_PIECES) - 1 {
-            return store.add_solution(pieces);
+            // a solution covers every cell, as the table strategies ensure by
+            // filling the first empty cell at every step
+            if current & self.board == self.board {
+                store.add_solution(pieces);
+            }
+            return;
         }
-        let target = current.trailing_ones() as usize;
-        for &(i, b) in &self.table[target][used] {
-            if current & b == 0 {
-                let next = current | b;
-                if self.holes[target].iter().any(|&(u, v)| next & u == v) {
+        let (live, rest) = buffer.split_at_mut(self.words);
+        let Some(row) = self.select(current, used, live) else {
+            return;
+        };
+        for (w, (&r, &l)) in row.iter().zip(&*live).enumerate() {
+            let mut bits = r & l;
+            while bits != 0 {
+                let (i, b) = self.placements[w * 64 + bits.trailing_zeros() as usize];
+                bits &= bits - 1;
+                let (next, used) = (current | b, used | (1 << i));
+                if self.regions.check(next, b, used) {
                     continue;
                 }
+                let next_live = &mut rest[..self.words];
+                next_live.copy_from_slice(live);
+                for (k, v) in self.piece_row(i).iter().enumerate() {
+                    next_live[k] &= !v;
+                }
+                let mut cells = b;
+                while cells != 0 {
+                    let row = self.cell_row(cells.trailing_zeros() as usize);
+                    for (k, v) in row.iter().enumerate() {
+                        next_live[k] &= !v;
+                    }
+                    cells &= cells - 1;
+                }
                 pieces[i] = b;
-                self.backtrack(next, used | (1 << i), pieces, store);
+                self.search(next, used, rest, pieces, store);
+            }
+        }
+    }
+}
+
+impl Strategy for MostConstrainedStrategy {
+    fn from_placements(
+        rows: usize,
+        cols: usize,
+        _order: &[usize],
+        placements: Vec<(usize, Bitboard)>,
+        regions: RegionChecker,
+    ) -> Self {
+        let words = placements.len().div_ceil(64);
+        let mut cells = vec![0; 64 * words];
+        let mut pieces = vec![0; NUM_PIECES * words];
+        for (j, &(i, b)) in placements.iter().enumerate() {
+            pieces[i * words + j / 64] |= 1 << (j % 64);
+            let mut u = b;
+            while u != 0 {
+                cells[u.trailing_zeros() as usize * words + j / 64] |= 1 << (j % 64);
+                u &= u - 1;
+            }
+        }
+        Self {
+            placements,
+            words,
+            cells,
+            pieces,
+            board: if rows * cols == 64 {
+                !0
+            } else {
+                (1 << (rows * cols)) - 1
+            },
+            regions,
+        }
+    }
+    fn regions(&self) -> &RegionChecker {
+        &self.regions
+    }
+    fn placements(&self) -> Vec<(usize, Bitboard)> {
+        self.placements.clone()
+    }
+    fn backtrack(
+        &self,
+        current: Bitboard,
+        used: usize,
+        pieces: &mut [Bitboard; NUM_PIECES],
+        store: &mut dyn SolutionStore,
+    ) {
+        // a row of legal placements for every depth of the search
+        let mut buffer = vec![0; self.words * (NUM_PIECES + 1)];
+        self.live(current, used, &mut buffer[..self.words]);
+        self.search(current, used, &mut buffer, pieces, store);
+    }
+    fn children(&self, current: Bitboard, used: usize, children: &mut Vec<(usize, Bitboard)>) {
+        let mut live = vec![0; self.words];
+        self.live(current, used, &mut live);
+        if let Some(row) = self.select(current, used, &live) {
+            for (w, (&r, &l)) in row.iter().zip(&live).enumerate() {
+                let mut bits = r & l;
+                while bits != 0 {
+                    let (i, b) = self.placements[w * 64 + bits.trailing_zeros() as usize];
+                    if !self.regions.check(current | b, b, used | (1 << i)) {
+                        children.push((i, b));
+                    }
+                    bits &= bits - 1;
+                }
             }
         }
     }
@@ -205,7 +716,29 @@ impl Checker {
             .zip(&self.edges)
             .any(|(units, edge)| {
                 let masked = u & edge;
-                masked != 0 && masked % units.0 != 0 && masked % units.1 == 0
+                masked != 0 && !masked.is_multiple_of(units.0) && masked.is_multiple_of(units.1)
             })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn region_checker() {
+        let checker = RegionChecker::new(3, 20);
+        let board = (1 << 60) - 1;
+        // I piece in the top row leaves a 55-cell region
+        let placed = 0b11111;
+        assert!(!checker.check(placed, placed, 1 << 0));
+        // filling the first column leaves a 57-cell region
+        let placed = 1 | (1 << 20) | (1 << 40);
+        assert!(checker.check(placed, placed, 0));
+        // a P-shaped 5-cell region at the left end
+        let region = 0b11 | (0b11 << 20) | (1 << 40);
+        assert!(!checker.check(board & !region, 1 << 41, 0));
+        assert!(checker.check(board & !region, 1 << 41, 1 << 1));
+        assert!(checker.check(board & !(region & !1), 1 << 41, 0));
+    }
+}
@@ -0,0 +1,218 @@
+use super::DefaultSolver;
+#[cfg(feature = "sat")]
+use super::{represent, Solver};
+#[cfg(feature = "sat")]
+use crate::sat::Incremental;
+use crate::sat::{Cnf, Lit};
+use crate::shapes::calculate_shapes;
+use crate::{Bitboard, Piece, NUM_PIECES};
+use std::collections::BTreeSet;
+
+/// Solves tilings as Boolean formulas.
+///
+/// Variable `k + 1` of the formula from `encode` places piece `i` on cells
+/// `b`, where `(i, b)` is the `k`th entry of `placements`. Every piece has
+/// exactly one placement and every empty cell is covered by exactly one, and
+/// further solutions are found by adding a clause that blocks the last one.
+///
+/// It implements `Solver` with the `sat` feature, through varisat, which takes
+/// seconds to prove that no tiling is left where the search solvers take
+/// milliseconds. Without the feature, formulas from `encode` can still be
+/// solved externally and read back with `decode`.
+pub struct SatSolver {
+    rows: usize,
+    cols: usize,
+    placements: Vec<(usize, Bitboard)>,
+    /// Cell maps of the board's symmetries, starting with the identity.
+    symmetries: Vec<Vec<usize>>,
+}
+
+impl SatSolver {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        assert!(rows * cols <= 64);
+        let shapes = calculate_shapes();
+        let mut placements = Vec::new();
+        for (n, shape) in shapes.iter().enumerate() {
+            for s in shape {
+                if s.iter().any(|&(x, y)| x >= cols || y >= rows) {
+                    continue;
+                }
+                let v = s.iter().map(|p| 1 << (p.0 + p.1 * cols)).sum::<u64>();
+                let (w, h) = s
+                    .iter()
+                    .fold((0, 0), |(xmax, ymax), &(x, y)| (xmax.max(x), ymax.max(y)));
+                for y in 0..rows - h {
+                    for x in 0..cols - w {
+                        placements.push((n, v << (x + y * cols)));
+                    }
+                }
+            }
+        }
+        placements.sort_unstable();
+        Self {
+            rows,
+            cols,
+            placements,
+            symmetries: DefaultSolver::new(rows, cols).symmetries(),
+        }
+    }
+    /// Placements as `(piece, bitboard)`, in variable order.
+    pub fn placements(&self) -> &[(usize, Bitboard)] {
+        &self.placements
+    }
+    /// Symmetries of the board that map `initial` to itself.
+    fn symmetries(&self, initial: Bitboard) -> Vec<&Vec<usize>> {
+        self.symmetries
+            .iter()
+            .filter(|g| DefaultSolver::apply(g, initial) == initial)
+            .collect()
+    }
+    /// The formula whose models are the solutions with the cells of `initial`
+    /// filled.
+    ///
+    /// With `symmetry_breaking`, X may only take the smallest placement of
+    /// every set of placements that the board's symmetries map onto each
+    /// other, which keeps at least one solution of every class.
+    pub fn encode(&self, initial: Bitboard, symmetry_breaking: bool) -> Cnf {
+        let mut cnf = Cnf::new(self.placements.len());
+        let symmetries = self.symmetries(initial);
+        let allowed = |&(i, b): &(usize, Bitboard)| {
+            b & initial == 0
+                && !(symmetry_breaking
+                    && i == Piece::X as usize
+                    && symmetries.iter().any(|g| DefaultSolver::apply(g, b) < b))
+        };
+        let mut by_piece = vec![Vec::new(); NUM_PIECES];
+        let mut by_cell = vec![Vec::new(); self.rows * self.cols];
+        for (k, p) in self.placements.iter().enumerate() {
+            let var = k as Lit + 1;
+            if !allowed(p) {
+                cnf.add_clause([-var]);
+                continue;
+            }
+            by_piece[p.0].push(var);
+            for (z, vars) in by_cell.iter_mut().enumerate() {
+                if p.1 & (1 << z) != 0 {
+                    vars.push(var);
+                }
+            }
+        }
+        // at least one placement of every piece and on every empty cell, and
+        // at most one of each, stated once for every pair of placements that
+        // share a piece or a cell
+        let mut conflicts = BTreeSet::new();
+        let empty = (0..self.rows * self.cols).filter(|z| initial & (1 << z) == 0);
+        for vars in by_piece.iter().chain(empty.map(|z| &by_cell[z])) {
+            cnf.add_clause(vars.iter().copied());
+            for (k, &a) in vars.iter().enumerate() {
+                conflicts.extend(vars[k + 1..].iter().map(|&b| (a, b)));
+            }
+        }
+        for (a, b) in conflicts {
+            cnf.add_clause([-a, -b]);
+        }
+        cnf
+    }
+    /// The solution in a model of a formula from `encode`, such as one read
+    /// with `sat::parse_model`, or `None` if it doesn't tile the board.
+    pub fn decode(&self, initial: Bitboard, model: &[bool]) -> Option<[Bitboard; NUM_PIECES]> {
+        let mut pieces = [0; NUM_PIECES];
+        let mut covered = initial;
+        for (&(i, b), _) in self.placements.iter().zip(model).filter(|(_, &v)| v) {
+            if pieces[i] != 0 || covered & b != 0 {
+                return None;
+            }
+            pieces[i] = b;
+            covered |= b;
+        }
+        (covered.count_ones() as usize == self.rows * self.cols).then_some(pieces)
+    }
+    #[cfg(feature = "sat")]
+    fn clause(&self, pieces: &[Bitboard; NUM_PIECES]) -> Vec<Lit> {
+        pieces
+            .iter()
+            .enumerate()
+            .map(|(i, &b)| -(self.placements.binary_search(&(i, b)).unwrap() as Lit + 1))
+            .collect()
+    }
+}
+
+#[cfg(feature = "sat")]
+impl Solver for SatSolver {
+    fn solve(&self, initial: Bitboard, unique: bool) -> Vec<[Bitboard; NUM_PIECES]> {
+        let symmetries = self.symmetries(initial);
+        let cnf = self.encode(initial, unique);
+        let mut sat = Incremental::new(&cnf);
+        let mut ret = Vec::new();
+        while let Some(model) = sat.solve() {
+            let Some(pieces) = self.decode(initial, &model) else {
+                // the formula only has tilings as models, but a model that
+                // isn't one is blocked and skipped rather than returned
+                let true_vars = model.iter().enumerate().filter(|(_, &v)| v);
+                sat.add_clause(&true_vars.map(|(k, _)| -(k as Lit + 1)).collect::<Vec<_>>());
+                continue;
+            };
+            if unique {
+                let images = symmetries
+                    .iter()
+                    .map(|g| pieces.map(|b| DefaultSolver::apply(g, b)))
+                    .collect::<Vec<_>>();
+                for image in &images {
+                    sat.add_clause(&self.clause(image));
+                }
+                // the smallest image that keeps X where symmetry breaking put
+                // it, as `DefaultSolver` returns
+                let x = Piece::X as usize;
+                ret.extend(images.into_iter().filter(|s| s[x] == pieces[x]).min());
+            } else {
+                sat.add_clause(&self.clause(&pieces));
+                ret.push(pieces);
+            }
+        }
+        ret
+    }
+    fn represent_solution(&self, solution: &[Bitboard; NUM_PIECES]) -> Vec<Vec<Option<Piece>>> {
+        represent(self.rows, self.cols, solution)
+    }
+}
+
+#[cfg(all(test, feature = "sat"))]
+mod tests {
+    use super::*;
+    use crate::sat::parse_model;
+
+    #[test]
+    fn matches_default_solver() {
+        // proving that no other tiling is left takes varisat much longer than
+        // the search solvers, so only the board with fewest solutions
+        let expected = DefaultSolver::new(20, 3).solve(0, true);
+        let solutions = SatSolver::new(20, 3).solve(0, true);
+        assert_eq!(solutions.len(), expected.len());
+        assert_eq!(
+            solutions.iter().collect::<BTreeSet<_>>(),
+            expected.iter().collect()
+        );
+    }
+
+    #[test]
+    fn dimacs_round_trip() {
+        let solver = SatSolver::new(20, 3);
+        let cnf = solver.encode(0, true);
+        let dimacs = cnf.to_dimacs();
+        assert!(dimacs.starts_with(&format!("p cnf {} ", cnf.num_vars())));
+        let model = Cnf::from_dimacs(&dimacs).unwrap().solve().unwrap();
+
+        // as an external solver would print it
+        let output = model
+            .iter()
+            .enumerate()
+            .map(|(v, &t)| if t { v as i64 + 1 } else { -(v as i64 + 1) })
+            .map(|l| l.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let model = parse_model(&format!("s SATISFIABLE\nv {output} 0\n"), cnf.num_vars());
+        let pieces = solver.decode(0, &model.unwrap()).unwrap();
+        assert!(DefaultSolver::new(20, 3).solve(0, false).contains(&pieces));
+        assert_eq!(solver.decode(0, &vec![false; cnf.num_vars()]), None);
+    }
+}
@@ -1,7 +1,7 @@
-use super::Solver;
+use super::{represent, Solver};
 use crate::shapes::calculate_shapes;
+use crate::tables::{Kind, Reader, TableError, Writer};
 use crate::{Bitboard, Piece, NUM_PIECES};
-use num_traits::FromPrimitive;
 use std::array;
 
 pub struct SimpleSolver {
@@ -14,33 +14,59 @@ impl SimpleSolver {
     pub fn new(rows: usize, cols: usize) -> Self {
         assert!(rows * cols <= 64);
         let shapes = calculate_shapes();
-        let mut table = array::from_fn(|_| array::from_fn(|_| Vec::new()));
+        let mut placements = Vec::new();
         for (n, shape) in shapes.iter().enumerate() {
             for s in shape {
                 if s.iter().any(|&(x, y)| x >= cols || y >= rows) {
                     continue;
                 }
-                let v = s.iter().map(|p| (1 << (p.0 + p.1 * cols))).sum::<u64>();
+                let v = s.iter().map(|p| 1 << (p.0 + p.1 * cols)).sum::<u64>();
                 let (w, h) = s
                     .iter()
                     .fold((0, 0), |(xmax, ymax), &(x, y)| (xmax.max(x), ymax.max(y)));
                 for y in 0..rows - h {
                     for x in 0..cols - w {
-                        let offset = x + y * cols;
-                        table[s[0].0 + offset][n].push(v << offset);
+                        placements.push((n, v << (x + y * cols)));
                     }
                 }
             }
         }
+        Self::from_placements(rows, cols, &placements)
+    }
+    fn from_placements(rows: usize, cols: usize, placements: &[(usize, Bitboard)]) -> Self {
+        let mut table: [[Vec<Bitboard>; NUM_PIECES]; 64] =
+            array::from_fn(|_| array::from_fn(|_| Vec::new()));
+        for &(n, u) in placements {
+            table[u.trailing_zeros() as usize][n].push(u);
+        }
         Self { rows, cols, table }
     }
+    /// Serialises the placement tables into a binary blob.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer::new(Kind::Simple, self.rows, self.cols);
+        let placements = self
+            .table
+            .iter()
+            .flat_map(|t| t.iter().enumerate())
+            .flat_map(|(n, v)| v.iter().map(move |&u| (n, u)))
+            .collect::<Vec<_>>();
+        w.placements(&placements);
+        w.finish()
+    }
+    /// Loads a solver from a blob created by [`Self::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, TableError> {
+        let (mut r, rows, cols) = Reader::new(bytes, Kind::Simple)?;
+        let placements = r.placements()?;
+        r.finish()?;
+        Ok(Self::from_placements(rows, cols, &placements))
+    }
     fn backtrack(
         &self,
         current: Bitboard,
         solutions: &mut Vec<[Bitboard; NUM_PIECES]>,
         s: &mut [Bitboard; NUM_PIECES],
     ) {
-        if !s.iter().any(|u| *u == 0) {
+        if !s.contains(&0) {
             return solutions.push(*s);
         }
         let target = current.trailing_ones() as usize;
@@ -72,17 +98,6 @@ impl Solver for SimpleSolver {
         solutions
     }
     fn represent_solution(&self, solution: &[Bitboard; NUM_PIECES]) -> Vec<Vec<Option<Piece>>> {
-        let mut ret = vec![vec![None; self.cols]; self.rows];
-        for (i, b) in solution.iter().enumerate() {
-            let p = Piece::from_usize(i);
-            for (y, row) in ret.iter_mut().enumerate() {
-                for (x, col) in row.iter_mut().enumerate() {
-                    if b & (1 << (x + y * self.cols)) != 0 {
-                        *col = p;
-                    }
-                }
-            }
-        }
-        ret
+        represent(self.rows, self.cols, solution)
     }
 }
@@ -0,0 +1,211 @@
+//! Binary encoding of solver tables.
+//!
+//! A blob starts with the magic bytes `PNTB`, a format version and a solver
+//! kind, followed by the board size and the solver's placement tables. Every
+//! placement is stored once as a piece index and a bitboard; lookup structures
+//! derived from them are rebuilt when loading, but nothing that needs the
+//! shapes of the pieces.
+
+use crate::{Bitboard, NUM_PIECES};
+use derive_more::Display;
+
+const MAGIC: &[u8; 4] = b"PNTB";
+const VERSION: u8 = 2;
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum TableError {
+    #[display(fmt = "not a table blob")]
+    InvalidMagic,
+    #[display(fmt = "unsupported table format version {}", _0)]
+    UnsupportedVersion(u8),
+    #[display(fmt = "table blob is for another solver")]
+    KindMismatch,
+    #[display(fmt = "table blob is truncated")]
+    Truncated,
+    #[display(fmt = "table blob is corrupted")]
+    Corrupted,
+}
+
+impl std::error::Error for TableError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    Simple = 0,
+    Default = 1,
+    Optimized = 2,
+}
+
+pub(crate) struct Writer(Vec<u8>);
+
+impl Writer {
+    pub(crate) fn new(kind: Kind, rows: usize, cols: usize) -> Self {
+        let mut w = Self(MAGIC.to_vec());
+        w.u8(VERSION);
+        w.u8(kind as u8);
+        w.u8(rows as u8);
+        w.u8(cols as u8);
+        w
+    }
+    pub(crate) fn u8(&mut self, v: u8) {
+        self.0.push(v);
+    }
+    pub(crate) fn u64(&mut self, v: u64) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+    pub(crate) fn bytes(&mut self, v: &[u8]) {
+        self.0.extend_from_slice(&(v.len() as u32).to_le_bytes());
+        self.0.extend_from_slice(v);
+    }
+    pub(crate) fn bitboards(&mut self, v: &[Bitboard]) {
+        self.0.extend_from_slice(&(v.len() as u32).to_le_bytes());
+        v.iter().for_each(|&b| self.u64(b));
+    }
+    pub(crate) fn placements(&mut self, v: &[(usize, Bitboard)]) {
+        self.0.extend_from_slice(&(v.len() as u32).to_le_bytes());
+        for &(i, b) in v {
+            self.u8(i as u8);
+            self.u64(b);
+        }
+    }
+    pub(crate) fn finish(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    board: Bitboard,
+}
+
+impl<'a> Reader<'a> {
+    /// Checks the header and returns a reader for the payload along with the
+    /// board size.
+    pub(crate) fn new(bytes: &'a [u8], kind: Kind) -> Result<(Self, usize, usize), TableError> {
+        let mut r = Self { bytes, board: !0 };
+        if r.take(MAGIC.len())? != MAGIC {
+            return Err(TableError::InvalidMagic);
+        }
+        match r.u8()? {
+            VERSION => {}
+            v => return Err(TableError::UnsupportedVersion(v)),
+        }
+        if r.u8()? != kind as u8 {
+            return Err(TableError::KindMismatch);
+        }
+        let (rows, cols) = (r.u8()? as usize, r.u8()? as usize);
+        if rows == 0 || cols == 0 || rows * cols > 64 {
+            return Err(TableError::Corrupted);
+        }
+        r.board = !0 >> (64 - rows * cols);
+        Ok((r, rows, cols))
+    }
+    fn take(&mut self, n: usize) -> Result<&'a [u8], TableError> {
+        if self.bytes.len() < n {
+            return Err(TableError::Truncated);
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+    fn len(&mut self) -> Result<usize, TableError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()) as usize)
+    }
+    pub(crate) fn u8(&mut self) -> Result<u8, TableError> {
+        Ok(self.take(1)?[0])
+    }
+    pub(crate) fn u64(&mut self) -> Result<u64, TableError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+    pub(crate) fn bytes(&mut self) -> Result<&'a [u8], TableError> {
+        let n = self.len()?;
+        self.take(n)
+    }
+    pub(crate) fn bitboards(&mut self) -> Result<Vec<Bitboard>, TableError> {
+        (0..self.len()?)
+            .map(|_| match self.u64()? {
+                b if b & !self.board == 0 => Ok(b),
+                _ => Err(TableError::Corrupted),
+            })
+            .collect()
+    }
+    pub(crate) fn placements(&mut self) -> Result<Vec<(usize, Bitboard)>, TableError> {
+        (0..self.len()?)
+            .map(|_| {
+                let i = self.u8()? as usize;
+                let b = self.u64()?;
+                if i >= NUM_PIECES || b.count_ones() != 5 || b & !self.board != 0 {
+                    return Err(TableError::Corrupted);
+                }
+                Ok((i, b))
+            })
+            .collect()
+    }
+    /// Fails unless the whole blob has been read.
+    pub(crate) fn finish(self) -> Result<(), TableError> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(TableError::Corrupted)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solvers::*;
+    use crate::Solver;
+
+    #[test]
+    fn round_trip() {
+        let solver = DefaultSolver::new(8, 8);
+        let bytes = solver.to_bytes();
+        assert_eq!(DefaultSolver::from_bytes(&bytes).unwrap().to_bytes(), bytes);
+
+        let solver = SimpleSolver::new(3, 20);
+        let bytes = solver.to_bytes();
+        assert_eq!(SimpleSolver::from_bytes(&bytes).unwrap().to_bytes(), bytes);
+
+        for solver_type in [
+            OptimizedSolverType::SmallTable,
+            OptimizedSolverType::LargeTable,
+            OptimizedSolverType::MostConstrained,
+        ] {
+            for order in [
+                ScanOrder::Spiral,
+                ScanOrder::custom((0..60).rev().collect()).unwrap(),
+            ] {
+                let solver =
+                    OptimizedSolver::with_scan_order(3, 20, solver_type, order.clone()).unwrap();
+                let bytes = solver.to_bytes();
+                let loaded = OptimizedSolver::from_bytes(&bytes).unwrap();
+                assert_eq!(loaded.to_bytes(), bytes);
+                assert_eq!(loaded.scan_order(), &order);
+                assert_eq!(loaded.solve(0, false), solver.solve(0, false));
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_blobs() {
+        let bytes = OptimizedSolver::new(6, 10, OptimizedSolverType::SmallTable).to_bytes();
+        assert_eq!(
+            DefaultSolver::from_bytes(&bytes).err(),
+            Some(TableError::KindMismatch)
+        );
+        assert_eq!(
+            OptimizedSolver::from_bytes(&bytes[..bytes.len() - 1]).err(),
+            Some(TableError::Truncated)
+        );
+        assert_eq!(
+            OptimizedSolver::from_bytes(&bytes[1..]).err(),
+            Some(TableError::InvalidMagic)
+        );
+        let mut bytes = bytes;
+        bytes[4] = VERSION + 1;
+        assert_eq!(
+            OptimizedSolver::from_bytes(&bytes).err(),
+            Some(TableError::UnsupportedVersion(VERSION + 1))
+        );
+    }
+}
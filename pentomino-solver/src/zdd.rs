@@ -0,0 +1,329 @@
+//! Zero-suppressed decision diagrams of solution families.
+//!
+//! Following Knuth (*The Art of Computer Programming*, 7.1.4), all solutions
+//! of a board are compiled into a ZDD over placements: a node tests one
+//! placement, its HI branch holds the solutions using it and its LO branch
+//! those that don't. Placements are ordered by their first cell, and the
+//! diagram is built by a memoised search that always fills the first empty
+//! cell, so equal subproblems become shared nodes. Counting, uniform sampling,
+//! filtering and enumeration then work on the diagram without searching again.
+
+use crate::rng::Rng;
+use crate::{Bitboard, NUM_PIECES};
+use std::collections::HashMap;
+
+/// Index of a node; `BOTTOM` is the empty family, `TOP` the family holding
+/// only the empty set.
+type NodeId = u32;
+
+const BOTTOM: NodeId = 0;
+const TOP: NodeId = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Node {
+    var: u32,
+    lo: NodeId,
+    hi: NodeId,
+}
+
+#[derive(Debug, Clone)]
+pub struct Zdd {
+    /// Placements as `(piece, bitboard)`, in variable order.
+    vars: Vec<(usize, Bitboard)>,
+    /// Nodes after the two terminals, children always before parents.
+    nodes: Vec<Node>,
+    root: NodeId,
+}
+
+struct Builder<'a> {
+    table: &'a [[Vec<Bitboard>; NUM_PIECES]],
+    vars: HashMap<(usize, Bitboard), u32>,
+    nodes: Vec<Node>,
+    unique: HashMap<Node, NodeId>,
+    memo: HashMap<(Bitboard, usize), NodeId>,
+}
+
+impl Builder<'_> {
+    fn node(&mut self, var: u32, lo: NodeId, hi: NodeId) -> NodeId {
+        if hi == BOTTOM {
+            return lo;
+        }
+        let node = Node { var, lo, hi };
+        if let Some(&id) = self.unique.get(&node) {
+            return id;
+        }
+        let id = self.nodes.len() as NodeId + 2;
+        self.nodes.push(node);
+        self.unique.insert(node, id);
+        id
+    }
+    fn search(&mut self, current: Bitboard, used: usize) -> NodeId {
+        if used == (1 << NUM_PIECES) - 1 {
+            return TOP;
+        }
+        if let Some(&id) = self.memo.get(&(current, used)) {
+            return id;
+        }
+        let table = self.table;
+        let target = current.trailing_ones() as usize;
+        let mut candidates = Vec::new();
+        if let Some(row) = table.get(target) {
+            for (i, bs) in row.iter().enumerate() {
+                if used & (1 << i) == 0 {
+                    for &b in bs {
+                        if current & b == 0 {
+                            candidates.push((self.vars[&(i, b)], i, b));
+                        }
+                    }
+                }
+            }
+        }
+        // the placements at one cell form a chain of LO links, each HI
+        // branch continuing with the rest of the board
+        candidates.sort_unstable();
+        let mut id = BOTTOM;
+        for &(var, i, b) in candidates.iter().rev() {
+            let hi = self.search(current | b, used | (1 << i));
+            id = self.node(var, id, hi);
+        }
+        self.memo.insert((current, used), id);
+        id
+    }
+}
+
+impl Zdd {
+    /// Compiles all solutions with the cells of `initial` filled, given
+    /// placement tables indexed by first cell and piece.
+    pub(crate) fn build(table: &[[Vec<Bitboard>; NUM_PIECES]], initial: Bitboard) -> Self {
+        let mut vars = table
+            .iter()
+            .flat_map(|row| row.iter().enumerate())
+            .flat_map(|(i, bs)| bs.iter().map(move |&b| (i, b)))
+            .collect::<Vec<_>>();
+        vars.sort_unstable_by_key(|&(i, b)| (b.trailing_zeros(), i, b));
+        let mut builder = Builder {
+            table,
+            vars: vars
+                .iter()
+                .enumerate()
+                .map(|(v, &p)| (p, v as u32))
+                .collect(),
+            nodes: Vec::new(),
+            unique: HashMap::new(),
+            memo: HashMap::new(),
+        };
+        let root = builder.search(initial, 0);
+        Self {
+            vars,
+            nodes: builder.nodes,
+            root,
+        }
+    }
+    fn node(&self, id: NodeId) -> Node {
+        self.nodes[id as usize - 2]
+    }
+    /// Number of nodes, not counting the terminals.
+    pub fn size(&self) -> usize {
+        self.nodes.len()
+    }
+    /// Number of solutions below every node, indexed by node id.
+    pub(crate) fn counts(&self) -> Vec<u64> {
+        let mut counts = vec![0, 1];
+        for node in &self.nodes {
+            counts.push(counts[node.lo as usize] + counts[node.hi as usize]);
+        }
+        counts
+    }
+    pub fn count(&self) -> u64 {
+        self.counts()[self.root as usize]
+    }
+    /// A solution drawn uniformly at random, or `None` if there is none.
+    pub fn sample(&self, seed: u64) -> Option<[Bitboard; NUM_PIECES]> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+        let mut rng = Rng::new(seed);
+        self.unrank(&self.counts(), rng.below(count))
+    }
+    /// Position of the solution in the order of `for_each`, or `None` if it
+    /// isn't one of the family, given the `counts` of the diagram.
+    pub(crate) fn rank(&self, counts: &[u64], pieces: &[Bitboard; NUM_PIECES]) -> Option<u64> {
+        let mut r = 0;
+        let mut id = self.root;
+        while id > TOP {
+            let node = self.node(id);
+            let (i, b) = self.vars[node.var as usize];
+            if pieces[i] == b {
+                id = node.hi;
+            } else {
+                r += counts[node.hi as usize];
+                id = node.lo;
+            }
+        }
+        (id == TOP).then_some(r)
+    }
+    /// The solution at position `r` in the order of `for_each`, or `None` if
+    /// there are no more than `r`, given the `counts` of the diagram.
+    pub(crate) fn unrank(&self, counts: &[u64], mut r: u64) -> Option<[Bitboard; NUM_PIECES]> {
+        if r >= counts[self.root as usize] {
+            return None;
+        }
+        let mut pieces = [Bitboard::default(); NUM_PIECES];
+        let mut id = self.root;
+        while id != TOP {
+            let node = self.node(id);
+            if r < counts[node.hi as usize] {
+                let (i, b) = self.vars[node.var as usize];
+                pieces[i] = b;
+                id = node.hi;
+            } else {
+                r -= counts[node.hi as usize];
+                id = node.lo;
+            }
+        }
+        Some(pieces)
+    }
+    /// The solutions whose placements all satisfy `keep`.
+    ///
+    /// Since every solution places each piece once, this also expresses
+    /// constraints like "piece `i` covers cell `z`", by rejecting the other
+    /// placements of `i`.
+    pub fn retain(&self, keep: impl Fn(usize, Bitboard) -> bool) -> Self {
+        let mut map = vec![BOTTOM, TOP];
+        let mut nodes = Vec::new();
+        let mut unique = HashMap::new();
+        for node in &self.nodes {
+            let (i, b) = self.vars[node.var as usize];
+            let lo = map[node.lo as usize];
+            let hi = if keep(i, b) {
+                map[node.hi as usize]
+            } else {
+                BOTTOM
+            };
+            let id = if hi == BOTTOM {
+                lo
+            } else {
+                let node = Node {
+                    var: node.var,
+                    lo,
+                    hi,
+                };
+                *unique.entry(node).or_insert_with(|| {
+                    nodes.push(node);
+                    nodes.len() as NodeId + 1
+                })
+            };
+            map.push(id);
+        }
+        Self {
+            vars: self.vars.clone(),
+            nodes,
+            root: map[self.root as usize],
+        }
+        .compact()
+    }
+    /// Drops the nodes that are no longer reachable from the root.
+    fn compact(self) -> Self {
+        let mut reachable = vec![false; self.nodes.len() + 2];
+        reachable[self.root as usize] = true;
+        for id in (2..reachable.len()).rev() {
+            if reachable[id] {
+                let node = self.node(id as NodeId);
+                reachable[node.lo as usize] = true;
+                reachable[node.hi as usize] = true;
+            }
+        }
+        let mut map = vec![BOTTOM, TOP];
+        let mut nodes = Vec::new();
+        for (id, node) in self.nodes.iter().enumerate() {
+            if reachable[id + 2] {
+                nodes.push(Node {
+                    var: node.var,
+                    lo: map[node.lo as usize],
+                    hi: map[node.hi as usize],
+                });
+                map.push(nodes.len() as NodeId + 1);
+            } else {
+                map.push(BOTTOM);
+            }
+        }
+        Self {
+            vars: self.vars,
+            nodes,
+            root: map[self.root as usize],
+        }
+    }
+    /// Calls `f` with every solution.
+    pub fn for_each(&self, mut f: impl FnMut(&[Bitboard; NUM_PIECES])) {
+        let mut pieces = [Bitboard::default(); NUM_PIECES];
+        self.walk(self.root, &mut pieces, &mut f);
+    }
+    fn walk(
+        &self,
+        id: NodeId,
+        pieces: &mut [Bitboard; NUM_PIECES],
+        f: &mut impl FnMut(&[Bitboard; NUM_PIECES]),
+    ) {
+        match id {
+            BOTTOM => {}
+            TOP => f(pieces),
+            _ => {
+                let node = self.node(id);
+                let (i, b) = self.vars[node.var as usize];
+                pieces[i] = b;
+                self.walk(node.hi, pieces, f);
+                self.walk(node.lo, pieces, f);
+            }
+        }
+    }
+    pub fn solutions(&self) -> Vec<[Bitboard; NUM_PIECES]> {
+        let mut ret = Vec::new();
+        self.for_each(|pieces| ret.push(*pieces));
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::solvers::{DefaultSolver, OptimizedSolver, OptimizedSolverType};
+    use crate::{Piece, Solver};
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn agrees_with_solvers() {
+        let solver = DefaultSolver::new(20, 3);
+        let zdd = solver.zdd(0);
+        let solutions = zdd.solutions();
+        assert_eq!(zdd.count(), 8);
+        assert_eq!(
+            solutions.iter().collect::<BTreeSet<_>>(),
+            solver.solve(0, false).iter().collect()
+        );
+
+        let zdd = DefaultSolver::new(15, 4).zdd(0);
+        let optimized = OptimizedSolver::new(15, 4, OptimizedSolverType::SmallTable);
+        assert_eq!(zdd.count(), optimized.solve(0, false).len() as u64);
+    }
+
+    #[test]
+    fn sample_and_retain() {
+        let zdd = DefaultSolver::new(20, 3).zdd(0);
+        let solutions = zdd.solutions();
+        let mut hits = vec![0; solutions.len()];
+        for seed in 0..800 {
+            let sample = zdd.sample(seed).unwrap();
+            hits[solutions.iter().position(|s| s == &sample).unwrap()] += 1;
+        }
+        assert!(hits.iter().all(|&n| (50..150).contains(&n)), "{hits:?}");
+
+        // X in the top half
+        let x = Piece::X as usize;
+        let top = (1 << 30) - 1;
+        let filtered = zdd.retain(|i, b| i != x || b & top == b);
+        assert_eq!(filtered.count(), 4);
+        assert!(filtered.size() < zdd.size());
+        assert!(filtered.solutions().iter().all(|s| s[x] & top == s[x]));
+        assert_eq!(zdd.retain(|_, _| false).count(), 0);
+    }
+}